@@ -0,0 +1,139 @@
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+use web_sys::Document;
+
+thread_local! {
+    static CLOCK: RefCell<Clock> = RefCell::new(Clock::default());
+}
+
+/// Game time shared by every timer on the page. It stops while the player
+/// has paused or the tab is hidden, so no game loses time in the background.
+#[derive(Default)]
+struct Clock {
+    /// Storage key the paused flag persists under, once installed.
+    key: Option<String>,
+    /// Paused with the pause button (or restored paused from storage).
+    paused: bool,
+    /// The tab is in the background.
+    hidden: bool,
+    /// `performance.now()` when the clock last stopped.
+    stopped_at: Option<f64>,
+    /// Milliseconds spent stopped before `stopped_at`.
+    stopped_total: f64,
+}
+
+impl Clock {
+    fn is_running(&self) -> bool {
+        !self.paused && !self.hidden
+    }
+
+    fn now(&self) -> f64 {
+        let real = performance_now();
+        let stopped = self.stopped_at.map_or(0.0, |at| real - at);
+        real - self.stopped_total - stopped
+    }
+
+    /// Applies a pause or visibility change, keeping the stopped time out of
+    /// `now()` and the saved flag and pause UI up to date.
+    fn update(&mut self, paused: bool, hidden: bool) {
+        let was_running = self.is_running();
+        self.paused = paused;
+        self.hidden = hidden;
+        match (was_running, self.is_running()) {
+            (true, false) => self.stopped_at = Some(performance_now()),
+            (false, true) => {
+                if let Some(at) = self.stopped_at.take() {
+                    self.stopped_total += performance_now() - at;
+                }
+            }
+            _ => {}
+        }
+
+        // Leaving the page counts as pausing, so a reload comes back paused
+        if let (Some(key), Some(storage)) = (
+            &self.key,
+            web_sys::window().and_then(|w| w.local_storage().ok().flatten()),
+        ) {
+            let _ = storage.set_item(key, if paused || hidden { "true" } else { "false" });
+        }
+        if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+            self.render(&document);
+        }
+    }
+
+    fn render(&self, document: &Document) {
+        if let Some(body) = document.body() {
+            let _ = body.class_list().toggle_with_force("paused", self.paused);
+        }
+        if let Some(button) = document.get_element_by_id("pause") {
+            button.set_text_content(Some(if self.paused { "Resume" } else { "Pause" }));
+        }
+    }
+}
+
+fn performance_now() -> f64 {
+    web_sys::window()
+        .and_then(|w| w.performance())
+        .map_or(0.0, |p| p.now())
+}
+
+/// Whether game timers should advance right now.
+pub fn is_running() -> bool {
+    CLOCK.with(|clock| clock.borrow().is_running())
+}
+
+/// Milliseconds of game time, like `performance.now()` minus every stretch
+/// spent paused or hidden.
+pub fn now() -> f64 {
+    CLOCK.with(|clock| clock.borrow().now())
+}
+
+/// Starts the clock for a live game: restores the paused flag saved under
+/// `key`, and follows the tab's visibility and the page's `#pause` button.
+pub fn install(key: &str) -> Result<(), JsValue> {
+    // Games restart themselves on every level; the listeners only go on once
+    if CLOCK.with(|clock| clock.borrow().key.is_some()) {
+        return Ok(());
+    }
+    let window = web_sys::window().expect("no global window exists");
+    let document = window.document().expect("no document exists");
+    let paused = window
+        .local_storage()?
+        .and_then(|storage| storage.get_item(key).ok().flatten())
+        .is_some_and(|flag| flag == "true");
+
+    CLOCK.with(|clock| {
+        let mut clock = clock.borrow_mut();
+        clock.key = Some(key.to_string());
+        clock.update(paused, document.hidden());
+    });
+
+    let visibility_callback = {
+        let document = document.clone();
+        Closure::wrap(Box::new(move || {
+            CLOCK.with(|clock| {
+                let mut clock = clock.borrow_mut();
+                let paused = clock.paused;
+                clock.update(paused, document.hidden());
+            });
+        }) as Box<dyn FnMut()>)
+    };
+    document.add_event_listener_with_callback(
+        "visibilitychange",
+        visibility_callback.as_ref().unchecked_ref(),
+    )?;
+    visibility_callback.forget();
+
+    if let Some(button) = document.get_element_by_id("pause") {
+        let handler = Closure::wrap(Box::new(move |_event: web_sys::MouseEvent| {
+            CLOCK.with(|clock| {
+                let mut clock = clock.borrow_mut();
+                let (paused, hidden) = (!clock.paused, clock.hidden);
+                clock.update(paused, hidden);
+            });
+        }) as Box<dyn FnMut(_)>);
+        button.add_event_listener_with_callback("click", handler.as_ref().unchecked_ref())?;
+        handler.forget();
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Shepard–Metzler figures: ten cubes joined face to face in four straight
+//! arms, each at right angles to the one before.
+
+use crate::games::rng::Rng;
+
+/// Cubes each arm adds, in some order, to the one the figure starts from.
+const ARM_LENGTHS: [usize; 4] = [2, 2, 2, 3];
+
+/// A cube's position, in cube widths.
+pub(super) type Cube = [i32; 3];
+
+#[derive(Clone, Debug)]
+pub(super) struct Figure {
+    pub(super) cubes: Vec<Cube>,
+}
+
+/// The 24 ways to turn a cube onto itself, as a permutation of the axes and
+/// a sign for each: every signed permutation with determinant +1.
+fn cube_rotations() -> Vec<([usize; 3], [i32; 3])> {
+    const PERMUTATIONS: [([usize; 3], i32); 6] = [
+        ([0, 1, 2], 1),
+        ([1, 2, 0], 1),
+        ([2, 0, 1], 1),
+        ([0, 2, 1], -1),
+        ([2, 1, 0], -1),
+        ([1, 0, 2], -1),
+    ];
+    let mut rotations = Vec::new();
+    for &(axes, parity) in &PERMUTATIONS {
+        for bits in 0..8 {
+            let signs = [0, 1, 2].map(|i| if bits >> i & 1 == 1 { -1 } else { 1 });
+            if parity * signs.iter().product::<i32>() == 1 {
+                rotations.push((axes, signs));
+            }
+        }
+    }
+    rotations
+}
+
+impl Figure {
+    /// A random figure that is chiral, so its mirror image can't be turned
+    /// to match it.
+    pub(super) fn generate(rng: &mut Rng) -> Self {
+        loop {
+            if let Some(figure) = Self::try_generate(rng) {
+                if figure.is_chiral() {
+                    return figure;
+                }
+            }
+        }
+    }
+
+    /// Lays the arms out, or `None` if one runs back into the figure.
+    fn try_generate(rng: &mut Rng) -> Option<Self> {
+        let mut lengths = ARM_LENGTHS;
+        for i in (1..lengths.len()).rev() {
+            lengths.swap(i, rng.below(i + 1));
+        }
+
+        let mut cubes = vec![[0, 0, 0]];
+        let mut axis = rng.below(3);
+        for length in lengths {
+            let sign = if rng.random() < 0.5 { -1 } else { 1 };
+            for _ in 0..length {
+                let mut next = cubes[cubes.len() - 1];
+                next[axis] += sign;
+                if cubes.contains(&next) {
+                    return None;
+                }
+                cubes.push(next);
+            }
+            // The next arm turns through a right angle onto another axis
+            axis = (axis + 1 + rng.below(2)) % 3;
+        }
+        Some(Self { cubes })
+    }
+
+    pub(super) fn mirrored(&self) -> Self {
+        Self {
+            cubes: self.cubes.iter().map(|&[x, y, z]| [-x, y, z]).collect(),
+        }
+    }
+
+    pub(super) fn contains(&self, cube: Cube) -> bool {
+        self.cubes.contains(&cube)
+    }
+
+    /// The cubes in a canonical order, moved so the smallest coordinates
+    /// are zero, for comparing shapes wherever they sit.
+    fn normalized(cubes: &[Cube]) -> Vec<Cube> {
+        let min = [0, 1, 2].map(|i| cubes.iter().map(|cube| cube[i]).min().unwrap_or(0));
+        let mut cubes: Vec<Cube> = cubes
+            .iter()
+            .map(|cube| [0, 1, 2].map(|i| cube[i] - min[i]))
+            .collect();
+        cubes.sort_unstable();
+        cubes
+    }
+
+    fn is_chiral(&self) -> bool {
+        let shape = Self::normalized(&self.cubes);
+        let mirror = self.mirrored();
+        !cube_rotations().iter().any(|&(axes, signs)| {
+            let turned: Vec<Cube> = mirror
+                .cubes
+                .iter()
+                .map(|cube| [0, 1, 2].map(|i| signs[i] * cube[axes[i]]))
+                .collect();
+            Self::normalized(&turned) == shape
+        })
+    }
+
+    /// Mean position of the cubes' centres.
+    pub(super) fn centre(&self) -> [f64; 3] {
+        let n = self.cubes.len() as f64;
+        [0, 1, 2].map(|i| {
+            self.cubes
+                .iter()
+                .map(|cube| f64::from(cube[i]))
+                .sum::<f64>()
+                / n
+        })
+    }
+}
@@ -0,0 +1,87 @@
+//! The classic Shepard–Metzler mental rotation task: two figures of joined
+//! cubes are shown side by side, the second turned in depth, and the player
+//! judges whether it is the same object or its mirror image. The angle
+//! between them is the difficulty, and every answer is logged with its
+//! reaction time.
+
+use wasm_bindgen::prelude::*;
+use web_sys::Document;
+
+mod figure;
+mod render;
+
+use crate::games::{
+    rng::Rng,
+    trials::{Session, Task},
+};
+use figure::Figure;
+use render::Matrix;
+
+/// Degrees between the angles a trial can use; level `n` uses any multiple
+/// up to `n` steps, so level 9 reaches 180°.
+const ANGLE_STEP: u32 = 20;
+
+static TASK: Task = Task {
+    pause_key: "cube_rotation_paused",
+    level_key: "cube_rotation_level",
+    trials_key: "cube_rotation_trials",
+    max_level: 9,
+    draw: draw_pair,
+};
+
+#[wasm_bindgen]
+pub struct CubeRotation {
+    session: Session,
+}
+
+#[wasm_bindgen]
+impl CubeRotation {
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new() -> CubeRotation {
+        CubeRotation {
+            session: Session::new(&TASK),
+        }
+    }
+
+    pub fn start(&self) -> Result<(), JsValue> {
+        self.session.start()
+    }
+}
+
+impl Default for CubeRotation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A random starting view, turned about the vertical and tipped a little
+/// towards or away from the viewer, so pairs don't all share one pose.
+fn random_orientation(rng: &mut Rng) -> Matrix {
+    let turn = render::vertical_rotation(rng.random() * 360.0);
+    let tilt = render::rotation_about([1.0, 0.0, 0.0], rng.random() * 60.0 - 30.0);
+    render::then(&turn, &tilt)
+}
+
+/// Draws a fresh figure and its twin, turned in depth through an angle
+/// `level` allows.
+fn draw_pair(document: &Document, rng: &mut Rng, level: usize) -> (u32, bool) {
+    let figure = Figure::generate(rng);
+    let angle = ANGLE_STEP * rng.below(level + 1) as u32;
+    let mirrored = rng.random() < 0.5;
+    let base = random_orientation(rng);
+    let turned = render::then(&base, &render::vertical_rotation(f64::from(angle)));
+    let other = if mirrored {
+        figure.mirrored()
+    } else {
+        figure.clone()
+    };
+
+    if let Some(left) = document.get_element_by_id("figure-left") {
+        left.set_inner_html(&render::to_svg(&figure, &base));
+    }
+    if let Some(right) = document.get_element_by_id("figure-right") {
+        right.set_inner_html(&render::to_svg(&other, &turned));
+    }
+    (angle, mirrored)
+}
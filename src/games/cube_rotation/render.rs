@@ -0,0 +1,169 @@
+//! Isometric SVG drawings of a figure in any orientation.
+//!
+//! Each cube face that isn't hidden against a neighbour and faces the
+//! viewer is projected onto the screen and drawn back to front, shaded by
+//! how squarely it faces the light.
+
+use super::figure::{Cube, Figure};
+use std::{f64::consts::FRAC_1_SQRT_2, fmt::Write};
+
+/// A rotation, as a 3x3 matrix acting on column vectors.
+pub(super) type Matrix = [[f64; 3]; 3];
+type Vector = [f64; 3];
+
+/// The viewer looks down the diagonal from this corner, as in isometric
+/// drawings.
+const VIEW: Vector = [
+    0.577_350_269_189_625_8,
+    0.577_350_269_189_625_8,
+    0.577_350_269_189_625_8,
+];
+/// Screen axes: right along the x-z diagonal, and up with world y.
+const SCREEN_RIGHT: Vector = [FRAC_1_SQRT_2, 0.0, -FRAC_1_SQRT_2];
+const SCREEN_UP: Vector = [
+    -0.408_248_290_463_863,
+    0.816_496_580_927_726,
+    -0.408_248_290_463_863,
+];
+/// Light from above, a little to the left and front.
+const LIGHT: Vector = [
+    -0.267_261_241_912_424_4,
+    0.801_783_725_737_273_2,
+    0.534_522_483_824_848_8,
+];
+
+fn dot(a: Vector, b: Vector) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn apply(m: &Matrix, v: Vector) -> Vector {
+    [dot(m[0], v), dot(m[1], v), dot(m[2], v)]
+}
+
+/// `a` then `b`: the rotation that applies `a` first.
+pub(super) fn then(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, cell) in row.iter_mut().enumerate() {
+            *cell = (0..3).map(|k| b[i][k] * a[k][j]).sum();
+        }
+    }
+    out
+}
+
+/// Turning through `degrees` about the unit vector `axis`.
+pub(super) fn rotation_about(axis: Vector, degrees: f64) -> Matrix {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let [x, y, z] = axis;
+    let t = 1.0 - cos;
+    [
+        [t * x * x + cos, t * x * y - sin * z, t * x * z + sin * y],
+        [t * x * y + sin * z, t * y * y + cos, t * y * z - sin * x],
+        [t * x * z - sin * y, t * y * z + sin * x, t * z * z + cos],
+    ]
+}
+
+/// Turning in depth through `degrees` about the screen's vertical axis.
+pub(super) fn vertical_rotation(degrees: f64) -> Matrix {
+    rotation_about([0.0, 1.0, 0.0], degrees)
+}
+
+/// One visible face: how near the viewer its middle is, its shade class and
+/// its corners on screen.
+struct Face {
+    depth: f64,
+    shade: &'static str,
+    corners: [(f64, f64); 4],
+}
+
+/// The six faces of a cube, as their outward normal and two edges across.
+const FACES: [(Cube, Cube, Cube); 6] = [
+    ([1, 0, 0], [0, 1, 0], [0, 0, 1]),
+    ([-1, 0, 0], [0, 1, 0], [0, 0, 1]),
+    ([0, 1, 0], [1, 0, 0], [0, 0, 1]),
+    ([0, -1, 0], [1, 0, 0], [0, 0, 1]),
+    ([0, 0, 1], [1, 0, 0], [0, 1, 0]),
+    ([0, 0, -1], [1, 0, 0], [0, 1, 0]),
+];
+
+fn to_vector(cube: Cube) -> Vector {
+    cube.map(f64::from)
+}
+
+/// Distance from the figure's centre to its farthest corner, which no
+/// rotation changes, so that every drawing of it shares one scale.
+fn radius(figure: &Figure) -> f64 {
+    let centre = figure.centre();
+    figure
+        .cubes
+        .iter()
+        .map(|&cube| {
+            let offset = [0, 1, 2].map(|i| (f64::from(cube[i]) - centre[i]).abs() + 0.5);
+            dot(offset, offset).sqrt()
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Draws `figure` turned by `orientation` about its centre.
+pub(super) fn to_svg(figure: &Figure, orientation: &Matrix) -> String {
+    let centre = figure.centre();
+    let mut faces = Vec::new();
+    for &cube in &figure.cubes {
+        for &(normal, across, along) in &FACES {
+            // Faces against a neighbouring cube are inside the figure
+            if figure.contains([0, 1, 2].map(|i| cube[i] + normal[i])) {
+                continue;
+            }
+            let turned_normal = apply(orientation, to_vector(normal));
+            if dot(turned_normal, VIEW) <= 0.0 {
+                continue;
+            }
+
+            let point = |n: f64, a: f64, b: f64| {
+                let local = [0, 1, 2].map(|i| {
+                    f64::from(cube[i]) - centre[i]
+                        + 0.5
+                            * (n * f64::from(normal[i])
+                                + a * f64::from(across[i])
+                                + b * f64::from(along[i]))
+                });
+                apply(orientation, local)
+            };
+            let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                .map(|(a, b)| point(1.0, a, b))
+                .map(|p| (dot(p, SCREEN_RIGHT), -dot(p, SCREEN_UP)));
+            let brightness = dot(turned_normal, LIGHT);
+            faces.push(Face {
+                depth: dot(point(1.0, 0.0, 0.0), VIEW),
+                shade: if brightness > 0.5 {
+                    "shade-light"
+                } else if brightness > -0.1 {
+                    "shade-mid"
+                } else {
+                    "shade-dark"
+                },
+                corners,
+            });
+        }
+    }
+    // Painter's algorithm: farthest first
+    faces.sort_by(|a, b| a.depth.total_cmp(&b.depth));
+
+    let r = radius(figure);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{:.3} {:.3} {:.3} {:.3}\">",
+        -r,
+        -r,
+        2.0 * r,
+        2.0 * r
+    );
+    for face in faces {
+        let _ = write!(svg, "<polygon class=\"face {}\" points=\"", face.shade);
+        for (x, y) in face.corners {
+            let _ = write!(svg, "{x:.3},{y:.3} ");
+        }
+        svg.push_str("\"/>");
+    }
+    svg.push_str("</svg>");
+    svg
+}
@@ -0,0 +1,192 @@
+//! The mental rotation judgement with flat pieces: two polyominoes are
+//! shown, the second turned on the page, and the player says whether it is
+//! the first piece or its mirror image. Bigger pieces and wider angles make
+//! it harder, and every answer is logged with its reaction time.
+
+use super::{rotation, tile::Tile};
+use crate::games::{
+    rng::Rng,
+    trials::{Session, Task},
+};
+use std::fmt::Write;
+use wasm_bindgen::prelude::*;
+use web_sys::Document;
+
+/// Degrees between the angles a pair can differ by.
+const ANGLE_STEP: u32 = 30;
+
+static TASK: Task = Task {
+    pause_key: "polyomino_rotation_paused",
+    level_key: "polyomino_rotation_level",
+    trials_key: "polyomino_rotation_trials",
+    max_level: 8,
+    draw: draw_pair,
+};
+
+/// Cells in a piece at `level`: four at first, one more every other level.
+fn piece_size(level: usize) -> usize {
+    4 + (level - 1) / 2
+}
+
+/// The widest angle a pair can differ by at `level`: 60° at first and
+/// another step each level, up to 180°.
+fn max_angle(level: usize) -> u32 {
+    (ANGLE_STEP * (level as u32 + 1)).min(180)
+}
+
+/// Cells moved so the smallest coordinates are zero, in a canonical order,
+/// for comparing shapes wherever they sit.
+fn normalized(cells: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let min_x = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+    let min_y = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+    let mut cells: Vec<_> = cells.iter().map(|&(x, y)| (x - min_x, y - min_y)).collect();
+    cells.sort_unstable();
+    cells
+}
+
+/// The tile flipped left to right where it stands.
+fn mirror_image(tile: &Tile) -> Tile {
+    let min_x = tile.cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+    let max_x = tile.cells.iter().map(|&(x, _)| x).max().unwrap_or(0);
+    Tile {
+        cells: tile
+            .cells
+            .iter()
+            .map(|&(x, y)| (min_x + max_x - x, y))
+            .collect(),
+        ..tile.clone()
+    }
+}
+
+/// The tile's cells turned clockwise by `rotation` degrees about its pivot.
+fn turned(tile: &Tile, rotation: i32) -> Vec<(usize, usize)> {
+    rotation::rotate_coordinates(&tile.cells, tile.pivot(), rotation)
+        .expect("pieces are built far enough from the edge to turn freely")
+}
+
+/// Whether no turn of the mirror image matches the piece, so that "same"
+/// and "mirror" always have one right answer.
+fn is_chiral(tile: &Tile) -> bool {
+    let shape = normalized(&tile.cells);
+    let mirror = mirror_image(tile);
+    (0..4).all(|quarter| normalized(&turned(&mirror, quarter * 90)) != shape)
+}
+
+/// A random chiral piece of `size` cells, grown a cell at a time. It starts
+/// twice its size from the edges, so no turn or flip can push it off them.
+fn generate_piece(size: usize, rng: &mut Rng) -> Tile {
+    loop {
+        let mut cells = vec![(2 * size, 2 * size)];
+        while cells.len() < size {
+            let (x, y) = cells[rng.below(cells.len())];
+            let next = match rng.below(4) {
+                0 => (x + 1, y),
+                1 => (x - 1, y),
+                2 => (x, y + 1),
+                _ => (x, y - 1),
+            };
+            if !cells.contains(&next) {
+                cells.push(next);
+            }
+        }
+        let tile = Tile {
+            cells,
+            arrows: Vec::new(),
+            rotation: 0,
+            reversed: false,
+        };
+        if is_chiral(&tile) {
+            return tile;
+        }
+    }
+}
+
+/// Draws `cells` turned a further `degrees` clockwise about their middle.
+/// The view fits the piece at any angle, so both of a pair share one scale.
+fn to_svg(cells: &[(usize, usize)], degrees: u32) -> String {
+    let n = cells.len() as f64;
+    let centre_x = cells.iter().map(|&(x, _)| x as f64 + 0.5).sum::<f64>() / n;
+    let centre_y = cells.iter().map(|&(_, y)| y as f64 + 0.5).sum::<f64>() / n;
+    let radius = cells
+        .iter()
+        .map(|&(x, y)| {
+            let dx = (x as f64 + 0.5 - centre_x).abs() + 0.5;
+            let dy = (y as f64 + 0.5 - centre_y).abs() + 0.5;
+            dx.hypot(dy)
+        })
+        .fold(0.0, f64::max);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{:.3} {:.3} {:.3} {:.3}\">\
+         <g transform=\"rotate({degrees} {centre_x:.3} {centre_y:.3})\">",
+        centre_x - radius,
+        centre_y - radius,
+        2.0 * radius,
+        2.0 * radius
+    );
+    for &(x, y) in cells {
+        let _ = write!(
+            svg,
+            "<rect class=\"piece-cell\" x=\"{x}\" y=\"{y}\" width=\"1\" height=\"1\"/>"
+        );
+    }
+    svg.push_str("</g></svg>");
+    svg
+}
+
+#[wasm_bindgen]
+pub struct PolyominoRotation {
+    session: Session,
+}
+
+#[wasm_bindgen]
+impl PolyominoRotation {
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new() -> PolyominoRotation {
+        PolyominoRotation {
+            session: Session::new(&TASK),
+        }
+    }
+
+    pub fn start(&self) -> Result<(), JsValue> {
+        self.session.start()
+    }
+}
+
+impl Default for PolyominoRotation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Draws a fresh piece and its twin, sized and angled for `level`. The
+/// whole quarter turns move the second piece's cells; what is left over
+/// turns the drawing.
+fn draw_pair(document: &Document, rng: &mut Rng, level: usize) -> (u32, bool) {
+    let mut piece = generate_piece(piece_size(level), rng);
+    piece.cells = turned(&piece, 90 * rng.below(4) as i32);
+    let angle = ANGLE_STEP * rng.below(max_angle(level) as usize / ANGLE_STEP as usize + 1) as u32;
+    let mirrored = rng.random() < 0.5;
+    // Either way round, so the angle between them is all that matters
+    let clockwise = if rng.random() < 0.5 {
+        angle
+    } else {
+        (360 - angle) % 360
+    };
+    let other = if mirrored {
+        mirror_image(&piece)
+    } else {
+        piece.clone()
+    };
+    let quarter_turns = clockwise / 90 * 90;
+
+    if let Some(left) = document.get_element_by_id("figure-left") {
+        left.set_inner_html(&to_svg(&piece.cells, 0));
+    }
+    if let Some(right) = document.get_element_by_id("figure-right") {
+        let cells = turned(&other, quarter_turns as i32);
+        right.set_inner_html(&to_svg(&cells, clockwise - quarter_turns));
+    }
+    (angle, mirrored)
+}
@@ -1,220 +1,447 @@
+use super::levels::{self, Shape, Stage};
+use super::rotation::rotate_coordinates_for_tile;
+use super::solver::{self, Par};
 use super::tile::{Tile, Direction};
-use js_sys::Math;
+use crate::games::rng::Rng;
+
+// Fewest clicks a level must need, on grids big enough to ask for them
+const MIN_PAR: usize = 3;
+// Levels generated before settling for the best of them
+const MAX_ATTEMPTS: usize = 20;
+// Longest route, in cells per side of the grid
+const MAX_PATH_PER_SIDE: usize = 2;
+// Steps the route's random walk may take before giving up on it
+const MAX_WALK_STEPS: usize = 10_000;
+
+/// A freshly generated level: its scrambled tiles, which of them carry the
+/// route, where the rocket and earth are, and the fewest clicks that solve it.
+pub struct Level {
+    pub tiles: Vec<Tile>,
+    pub solution_path_tiles: Vec<usize>,
+    pub start_pos: (usize, usize),
+    pub end_pos: (usize, usize),
+    /// Sides of the grid the rocket and the earth sit beyond.
+    pub start_side: Direction,
+    pub end_side: Direction,
+    pub par: Par,
+}
 
 // Helper to generate random numbers
-fn random_usize(max: usize) -> usize {
-    (Math::random() * max as f64) as usize
-}
-
-// Random rotation: 0, 90, 180, or 270 degrees
-fn random_rotation() -> i32 {
-    let rand = random_usize(4);
-    match rand {
-        0 => 0,
-        1 => 90,
-        2 => 180,
-        _ => 270,
+fn random_usize(rng: &mut Rng, max: usize) -> usize {
+    rng.below(max)
+}
+
+// Random orthogonal arrow direction
+fn random_direction(rng: &mut Rng) -> Direction {
+    match random_usize(rng, 4) {
+        0 => Direction::North,
+        1 => Direction::East,
+        2 => Direction::South,
+        _ => Direction::West,
     }
 }
 
 // Random boolean for reversing tile directions
-fn random_bool() -> bool {
-    Math::random() > 0.5
+fn random_bool(rng: &mut Rng) -> bool {
+    rng.random() > 0.5
 }
 
-pub fn generate_level(level: usize) -> (Vec<Tile>, Vec<usize>, (usize, usize), (usize, usize)) {
-    // Grid size is determined by level (no minimum size)
-    let grid_size = level;
+pub fn generate_level(level: usize, rng: &mut Rng) -> Level {
+    let stage = levels::stage(level);
+    let grid_size = stage.size;
+    let min_par = grid_size.min(MIN_PAR);
     
-    // For very small grids (1-2), use fixed positions
-    let (start_y, end_y) = if grid_size < 3 {
-        (0, 0) // For tiny grids, just use a straight line
-    } else {
-        // Randomly select start and end Y positions
-        (random_usize(grid_size), random_usize(grid_size))
+    // How hard a candidate is known to be; an unsolvable one ranks below
+    // the rest
+    let depth = |par: Par| match par {
+        Par::Exact(clicks) | Par::AtLeast(clicks) => Some(clicks),
+        Par::Unsolvable => None,
     };
     
-    // Define start and end positions (start on left edge, end on right edge)
-    let start_pos = (0, start_y);
-    let end_pos = (grid_size.saturating_sub(1), end_y); // Use saturating_sub for safety with tiny grids
+    // Throw away levels that are solved in a click or two, or that the solver
+    // can't solve at all. If every attempt falls short, the hardest is kept
+    let mut hardest: Option<Level> = None;
+    for _ in 0..MAX_ATTEMPTS {
+        let mut candidate = generate_candidate(stage, rng);
+        candidate.par = match solver::par(&candidate.tiles, grid_size, candidate.start_pos, candidate.end_pos, candidate.end_side) {
+            // Every candidate is cut from a route that solves it, so one too
+            // big for the solver to finish counts as hard enough
+            Par::AtLeast(clicks) => Par::AtLeast(clicks.max(min_par)),
+            par => par,
+        };
+        if depth(candidate.par).is_some_and(|clicks| clicks >= min_par) {
+            return candidate;
+        }
+        if hardest.as_ref().is_none_or(|level| depth(level.par) < depth(candidate.par)) {
+            hardest = Some(candidate);
+        }
+    }
+    hardest.expect("MAX_ATTEMPTS is at least one")
+}
+
+fn generate_candidate(stage: &Stage, rng: &mut Rng) -> Level {
+    let grid_size = stage.size;
+    // Rocket and earth anywhere around the edge, in different cells
+    let (start_pos, start_side) = random_edge_cell(grid_size, rng);
+    let (mut end_pos, mut end_side) = random_edge_cell(grid_size, rng);
+    while end_pos == start_pos {
+        (end_pos, end_side) = random_edge_cell(grid_size, rng);
+    }
     
     // Create a path from start to end
-    let path = generate_path(start_pos, end_pos, grid_size);
+    let path = generate_path(start_pos, end_pos, stage, rng);
     
     // Generate tiles along the path
-    let (tiles, solution_path_tiles) = generate_tiles_from_path(path, grid_size);
+    let (tiles, solution_path_tiles) = generate_tiles_from_path(path, end_side, stage, rng);
     
     // Randomize tile rotations and reversals to increase difficulty
-    let tiles = randomize_tiles(tiles, solution_path_tiles.clone());
+    let tiles = randomize_tiles(tiles, solution_path_tiles.clone(), grid_size, rng);
     
-    (tiles, solution_path_tiles, start_pos, end_pos)
+    Level {
+        tiles,
+        solution_path_tiles,
+        start_pos,
+        end_pos,
+        start_side,
+        end_side,
+        par: Par::Unsolvable,
+    }
 }
 
-fn generate_path(start: (usize, usize), end: (usize, usize), grid_size: usize) -> Vec<(usize, usize)> {
-    let mut path = Vec::new();
-    path.push(start);
-    
-    let mut current = start;
+// A random cell on the edge of the grid, and the side it lies on
+fn random_edge_cell(grid_size: usize, rng: &mut Rng) -> ((usize, usize), Direction) {
+    let side = random_direction(rng);
+    let along = random_usize(rng, grid_size);
+    let last = grid_size - 1;
+    let cell = match side {
+        Direction::North => (along, 0),
+        Direction::South => (along, last),
+        Direction::West => (0, along),
+        _ => (last, along),
+    };
+    (cell, side)
+}
+
+// Orthogonal neighbours of `cell` that lie inside the grid, in random order
+fn shuffled_neighbours(cell: (usize, usize), grid_size: usize, rng: &mut Rng) -> Vec<(usize, usize)> {
+    let mut cells = neighbours(cell, grid_size);
+    for i in (1..cells.len()).rev() {
+        cells.swap(i, random_usize(rng, i + 1));
+    }
+    cells
+}
+
+fn generate_path(start: (usize, usize), end: (usize, usize), stage: &Stage, rng: &mut Rng) -> Vec<(usize, usize)> {
+    // A random walk that never crosses itself and backs up out of dead ends,
+    // so the route winds north, south and back west as it pleases. Routes
+    // are at least as long as the grid is wide, short enough to leave room
+    // for distractors, and can be cut into the stage's number of tiles
+    let grid_size = stage.size;
+    let min_len = grid_size.max(stage.tiles);
+    let max_len = (MAX_PATH_PER_SIDE * grid_size).min(3 * stage.tiles).max(min_len);
+    let mut path = vec![start];
+    // Neighbours of each cell on the path not yet tried from it
+    let mut untried = vec![shuffled_neighbours(start, grid_size, rng)];
     
-    // Create a path that first moves horizontally to the last column
-    while current.0 < end.0 {
-        // Always move east (right)
-        let next_x = current.0 + 1;
-        
-        // Occasionally adjust Y position to make the path more interesting
-        let mut next_y = current.1;
-        
-        // After the first move, potentially adjust Y to move toward the end Y position
-        if current.0 > start.0 && current.0 < end.0 - 1 {
-            if Math::random() < 0.3 {
-                // Move Y toward end position
-                if next_y < end.1 {
-                    next_y += 1;
-                } else if next_y > end.1 {
-                    next_y -= 1;
-                }
-            }
+    for _ in 0..MAX_WALK_STEPS {
+        let Some(&current) = path.last() else {
+            break;
+        };
+        if current == end {
+            return path;
         }
         
-        // Make sure we stay within grid bounds
-        next_y = next_y.min(grid_size - 1);
-        
-        // If this is the final horizontal move, ensure Y matches the end position
-        if next_x == end.0 {
-            next_y = end.1;
+        let next = if path.len() < max_len {
+            untried.last_mut().and_then(Vec::pop)
+        } else {
+            None
+        };
+        match next {
+            Some(next) if !path.contains(&next) && (next != end || path.len() + 1 >= min_len) => {
+                path.push(next);
+                untried.push(shuffled_neighbours(next, grid_size, rng));
+            }
+            Some(_) => {}
+            None => {
+                // Nowhere left to go from here
+                path.pop();
+                untried.pop();
+            }
         }
-        
-        let next = (next_x, next_y);
-        path.push(next);
-        current = next;
     }
     
-    // Make sure the last point matches the end position exactly
-    if let Some(&last) = path.last() {
-        if last != end {
-            path.pop();
-            path.push(end);
-        }
+    // The walk ran out of steps, so go straight there instead
+    direct_path(start, end, rng)
+}
+
+fn direct_path(start: (usize, usize), end: (usize, usize), rng: &mut Rng) -> Vec<(usize, usize)> {
+    let mut path = vec![start];
+    let mut current = start;
+    
+    // Head for the end one orthogonal step at a time, mostly across and
+    // occasionally up or down, never moving away from it
+    while current != end {
+        let (x, y) = current;
+        let across = x != end.0 && (y == end.1 || rng.random() >= 0.3);
+        current = if across {
+            (if x < end.0 { x + 1 } else { x - 1 }, y)
+        } else {
+            (x, if y < end.1 { y + 1 } else { y - 1 })
+        };
+        path.push(current);
     }
     
     path
 }
 
-fn generate_tiles_from_path(path: Vec<(usize, usize)>, grid_size: usize) -> (Vec<Tile>, Vec<usize>) {
+// Direction an arrow must point in to lead from `from` to the neighbouring `to`
+fn direction_between(from: (usize, usize), to: (usize, usize)) -> Direction {
+    if to.0 > from.0 {
+        Direction::East
+    } else if to.0 < from.0 {
+        Direction::West
+    } else if to.1 > from.1 {
+        Direction::South
+    } else {
+        Direction::North
+    }
+}
+
+// Orthogonal neighbours of `cell` that lie inside the grid
+fn neighbours((x, y): (usize, usize), grid_size: usize) -> Vec<(usize, usize)> {
+    [Direction::North, Direction::East, Direction::South, Direction::West]
+        .iter()
+        .filter_map(|direction| direction.step((x, y), grid_size))
+        .collect()
+}
+
+// The tetromino four consecutive path cells bend into, if an L, J, S or Z.
+// A chain of four spans 2x3 exactly when it is one of those; straight
+// chains span 1x4 and ones that double back on themselves 2x2. An L keeps
+// going the same way for two steps, where an S or Z turns at every cell
+fn tetromino_shape(cells: &[(usize, usize)]) -> Option<Shape> {
+    let span = |coord: fn(&(usize, usize)) -> usize| {
+        let min = cells.iter().map(coord).min().unwrap_or(0);
+        let max = cells.iter().map(coord).max().unwrap_or(0);
+        max - min + 1
+    };
+    let (width, height) = (span(|c| c.0), span(|c| c.1));
+    if cells.len() != 4 || width * height != 6 {
+        return None;
+    }
+    let steps: Vec<Direction> = cells.windows(2).map(|pair| direction_between(pair[0], pair[1])).collect();
+    Some(if steps[0] == steps[1] || steps[1] == steps[2] { Shape::L } else { Shape::Skew })
+}
+
+fn generate_tiles_from_path(path: Vec<(usize, usize)>, exit: Direction, stage: &Stage, rng: &mut Rng) -> (Vec<Tile>, Vec<usize>) {
+    // Each path cell must point at the next one; the last points off the
+    // grid to the earth
+    let directions: Vec<Direction> = path
+        .windows(2)
+        .map(|pair| direction_between(pair[0], pair[1]))
+        .chain(std::iter::once(exit))
+        .collect();
+    
+    // Every tile on the path is part of the solution
+    let mut occupied = path.clone();
+    let mut tiles = cut_into_tiles(&path, &directions, stage.tiles, &stage.shapes, &mut occupied, stage.size, rng);
+    let solution_path_tiles = (0..tiles.len()).collect();
+    
+    // Add some additional non-path tiles to make the puzzle more challenging
+    add_distractor_tiles(&mut tiles, &path, &mut occupied, stage, rng);
+    
+    (tiles, solution_path_tiles)
+}
+
+// Cuts a route, with the arrow each of its cells needs, into `pieces` tiles
+// already pointing the way it goes, or as near as the route's length
+// allows: L, S and Z tetrominoes round its bends and T tetrominoes with a
+// stub off it where `shapes` allows them, and smaller pieces of 1-3 cells.
+// Stubs are taken from the free cells and added to `occupied`
+fn cut_into_tiles(
+    route: &[(usize, usize)],
+    directions: &[Direction],
+    pieces: usize,
+    shapes: &[Shape],
+    occupied: &mut Vec<(usize, usize)>,
+    grid_size: usize,
+    rng: &mut Rng,
+) -> Vec<Tile> {
     let mut tiles = Vec::new();
-    let mut solution_path_tiles = Vec::new();
-    let mut path_cells = path.clone();
-    
-    // Process the path to create tiles
-    while !path_cells.is_empty() {
-        // Determine the size of this polyomino tile (1-3 cells)
-        let tile_size = if path_cells.len() >= 3 {
-            match random_usize(3) {
-                0 => 1,
-                1 => 2,
-                _ => 3,
-            }
-        } else if path_cells.len() == 2 {
-            random_usize(2) + 1
-        } else {
-            1
-        };
+    let mut i = 0;
+    while i < route.len() {
+        let remaining = route.len() - i;
+        let mut cells = Vec::new();
+        let mut arrows = Vec::new();
         
-        // Take cells for this tile
-        let mut tile_cells = Vec::new();
-        for _ in 0..tile_size {
-            if let Some(cell) = path_cells.first() {
-                tile_cells.push(*cell);
-                path_cells.remove(0);
-            } else {
-                break;
+        // Fewest and most route cells this tile can carry and still leave
+        // one to three for each tile after it
+        let left = pieces.saturating_sub(tiles.len()).max(1);
+        let most = remaining - (left - 1).min(remaining - 1);
+        let least = remaining.saturating_sub(3 * (left - 1)).clamp(1, most.min(3));
+        let fits = |n: usize| (least..=most).contains(&n);
+        
+        match random_usize(rng, 3) {
+            // An L, S or Z carrying four cells of the route round its bends
+            0 if fits(4) && tetromino_shape(&route[i..i + 4]).is_some_and(|shape| shapes.contains(&shape)) => {
+                cells.extend_from_slice(&route[i..i + 4]);
+                arrows.extend_from_slice(&directions[i..i + 4]);
             }
+            // A T: three cells of the route around its centre, plus a
+            // stub off the route whose arrow leads nowhere
+            1 if fits(3) && shapes.contains(&Shape::T) => {
+                let centre = route[i + 1];
+                let stubs: Vec<_> = neighbours(centre, grid_size)
+                    .into_iter()
+                    .filter(|cell| !occupied.contains(cell))
+                    .collect();
+                if !stubs.is_empty() {
+                    let stub = stubs[random_usize(rng, stubs.len())];
+                    occupied.push(stub);
+                    cells.extend_from_slice(&route[i..i + 3]);
+                    arrows.extend_from_slice(&directions[i..i + 3]);
+                    cells.push(stub);
+                    arrows.push(direction_between(centre, stub));
+                }
+            }
+            _ => {}
         }
         
-        if !tile_cells.is_empty() {
-            // Create a new tile
-            let tile = Tile {
-                cells: tile_cells,
-                rotation: 0,
-                reversed: false,
-            };
-            
-            // Add to the tiles list
-            tiles.push(tile);
-            
-            // This tile is part of the solution path
-            solution_path_tiles.push(tiles.len() - 1);
+        // Otherwise a smaller piece of 1-3 cells, straight or bent
+        if cells.is_empty() {
+            let tile_size = least + random_usize(rng, most.min(3) - least + 1);
+            cells.extend_from_slice(&route[i..i + tile_size]);
+            arrows.extend_from_slice(&directions[i..i + tile_size]);
         }
+        i += cells.iter().filter(|cell| route.contains(cell)).count();
+        
+        tiles.push(Tile {
+            cells,
+            arrows,
+            rotation: 0,
+            reversed: false,
+        });
     }
-    
-    // Add some additional non-path tiles to make the puzzle more challenging
-    add_distractor_tiles(&mut tiles, &path, grid_size);
-    
-    (tiles, solution_path_tiles)
+    tiles
 }
 
-fn add_distractor_tiles(tiles: &mut Vec<Tile>, path: &[(usize, usize)], grid_size: usize) {
-    // Add "distractor" tiles that aren't part of the solution path
-    // The number of distractors scales with level difficulty
-    let num_distractors = (grid_size / 2).max(1);
-    
-    let mut occupied_cells: Vec<(usize, usize)> = path.to_vec();
-    for tile in tiles.iter() {
-        occupied_cells.extend(&tile.cells);
-    }
-    
-    for _ in 0..num_distractors {
-        // Try to place a distractor tile in an empty cell
+fn add_distractor_tiles(
+    tiles: &mut Vec<Tile>,
+    path: &[(usize, usize)],
+    occupied: &mut Vec<(usize, usize)>,
+    stage: &Stage,
+    rng: &mut Rng,
+) {
+    // Add "distractor" tiles that aren't part of the solution path, as
+    // many as the stage asks for
+    let grid_size = stage.size;
+    for _ in 0..stage.distractors {
+        // Try to branch a dead end off the path
         for _ in 0..10 { // Limit attempts to avoid infinite loops
-            let x = random_usize(grid_size);
-            let y = random_usize(grid_size);
-            
-            if !occupied_cells.contains(&(x, y)) {
-                let mut tile_cells = vec![(x, y)];
-                occupied_cells.push((x, y));
-                
-                // Add more cells to make a multi-cell distractor (50% chance)
-                if Math::random() < 0.5 && x + 1 < grid_size && !occupied_cells.contains(&(x + 1, y)) {
-                    tile_cells.push((x + 1, y));
-                    occupied_cells.push((x + 1, y));
-                }
-                
-                let tile = Tile {
-                    cells: tile_cells,
-                    rotation: 0,
-                    reversed: false,
-                };
+            if let Some(spur) = dead_end_spur(path, occupied, grid_size, rng) {
+                // Each cell points at the next, and the last keeps pointing
+                // onwards: turning the path tile it branches off to point
+                // into it looks like progress until the route runs out
+                let steps: Vec<Direction> = spur
+                    .windows(2)
+                    .map(|pair| direction_between(pair[0], pair[1]))
+                    .collect();
+                let arrows: Vec<Direction> = steps.iter().skip(1).chain(steps.last()).copied().collect();
                 
-                tiles.push(tile);
+                let spur = &spur[1..];
+                occupied.extend_from_slice(spur);
+                // Cut the same way as the path, so its tiles look just like
+                // the real route's
+                let pieces = spur.len().div_ceil(2);
+                tiles.extend(cut_into_tiles(spur, &arrows, pieces, &stage.shapes, occupied, grid_size, rng));
                 break;
             }
         }
     }
 }
 
-fn randomize_tiles(mut tiles: Vec<Tile>, solution_path_tiles: Vec<usize>) -> Vec<Tile> {
+// A short route of free cells leading off the side of the path and
+// stopping dead, starting from the path cell it branches off. `None` if
+// the chosen path cell has no free cell beside it
+fn dead_end_spur(
+    path: &[(usize, usize)],
+    occupied: &[(usize, usize)],
+    grid_size: usize,
+    rng: &mut Rng,
+) -> Option<Vec<(usize, usize)>> {
+    let mut spur = vec![path[random_usize(rng, path.len())]];
+    
+    // Wander 2-4 cells through free space
+    let length = 2 + random_usize(rng, 3);
+    while spur.len() <= length {
+        let last = spur[spur.len() - 1];
+        let choices: Vec<_> = neighbours(last, grid_size)
+            .into_iter()
+            .filter(|next| !occupied.contains(next) && !spur.contains(next))
+            .collect();
+        if choices.is_empty() {
+            break;
+        }
+        spur.push(choices[random_usize(rng, choices.len())]);
+    }
+    (spur.len() > 1).then_some(spur)
+}
+
+// Every cell the tile covers in any of its four orientations, or `None`
+// if some orientation leaves the grid
+fn swept_cells(tile: &Tile, grid_size: usize) -> Option<Vec<(usize, usize)>> {
+    let mut turned = tile.clone();
+    let mut swept = tile.cells.clone();
+    for _ in 0..3 {
+        let cells = rotate_coordinates_for_tile(&turned)?;
+        if cells.iter().any(|&(x, y)| x >= grid_size || y >= grid_size) {
+            return None;
+        }
+        swept.extend(&cells);
+        turned.rotate();
+    }
+    Some(swept)
+}
+
+fn randomize_tiles(mut tiles: Vec<Tile>, solution_path_tiles: Vec<usize>, grid_size: usize, rng: &mut Rng) -> Vec<Tile> {
+    // Rotating moves a tile's cells, so a tile is only turned if it can go
+    // all the way round without leaving the grid or meeting another tile.
+    // Cells it sweeps are claimed so no later tile turns through them, and
+    // every tile can then be turned back whatever order it's done in
+    let mut claimed: Vec<(usize, usize)> = tiles.iter().flat_map(|tile| tile.cells.clone()).collect();
+    let turnable = |tile: &Tile, claimed: &mut Vec<(usize, usize)>| {
+        let swept = swept_cells(tile, grid_size)?;
+        let clear = swept
+            .iter()
+            .all(|cell| tile.cells.contains(cell) || !claimed.contains(cell));
+        clear.then(|| claimed.extend(swept))
+    };
+    
     // Randomize rotations and reversals for maximum challenge
     for i in 0..tiles.len() {
+        let can_turn = turnable(&tiles[i], &mut claimed).is_some();
+        
         // Make sure solution path tiles require manipulation
         // This ensures we maximize the difference between best and worst case
         if solution_path_tiles.contains(&i) {
             // Always require some manipulation for solution tiles
-            if random_bool() {
-                // Apply rotation
-                let rotation = match random_usize(3) {
-                    0 => 90,
-                    1 => 180,
-                    _ => 270,
-                };
-                tiles[i].rotation = rotation;
+            if can_turn && random_bool(rng) {
+                // Turn it away from its solved orientation
+                for _ in 0..=random_usize(rng, 3) {
+                    tiles[i].rotate();
+                }
             } else {
                 // Apply reversal
                 tiles[i].reversed = true;
             }
         } else {
             // For non-solution tiles, randomize completely
-            tiles[i].rotation = random_rotation();
-            tiles[i].reversed = random_bool();
+            if can_turn {
+                for _ in 0..random_usize(rng, 4) {
+                    tiles[i].rotate();
+                }
+            }
+            tiles[i].reversed = random_bool(rng);
         }
     }
     
@@ -0,0 +1,60 @@
+//! Mental Rotation's level schedule in `levels.json`: each level's grid
+//! size, how many tiles the route is cut into and in which shapes, how many
+//! dead ends lead off it, and the time limit. Boards stop growing at the
+//! last row, so they never outgrow the screen.
+
+use crate::games::schedule;
+use serde::Deserialize;
+use std::sync::LazyLock;
+
+/// Smallest grid the generator can place the rocket and earth around.
+const MIN_SIZE: usize = 3;
+
+static SCHEDULE: LazyLock<Vec<Stage>> = LazyLock::new(|| {
+    let stages: Vec<Stage> = schedule::schedule(include_str!("levels.json"));
+    assert!(
+        stages.iter().all(|stage| stage.size >= MIN_SIZE),
+        "every level needs a grid at least {} cells across",
+        MIN_SIZE
+    );
+    stages
+});
+
+fn default_seconds() -> u32 {
+    180
+}
+
+/// Tetrominoes the route can be cut into, on top of the straight and bent
+/// pieces of one to three cells that every level uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum Shape {
+    /// Three cells in a line with one more at the end, round a corner.
+    L,
+    /// The S and Z, stepping sideways twice.
+    Skew,
+    /// Three cells of the route with a stub off the middle one.
+    T,
+}
+
+/// One row of the schedule.
+#[derive(Clone, Debug, Deserialize)]
+pub(super) struct Stage {
+    /// Cells along each side of the grid.
+    pub(super) size: usize,
+    /// Tiles the route is cut into, as near as its length allows.
+    pub(super) tiles: usize,
+    #[serde(default)]
+    pub(super) shapes: Vec<Shape>,
+    /// Dead ends branching off the route.
+    #[serde(default)]
+    pub(super) distractors: usize,
+    /// Time limit in seconds.
+    #[serde(default = "default_seconds")]
+    pub(super) seconds: u32,
+}
+
+/// The board settings for `level`, counted from 1.
+pub(super) fn stage(level: usize) -> &'static Stage {
+    schedule::stage(&SCHEDULE, level)
+}
@@ -3,20 +3,42 @@ mod level_generator;
 mod grid;
 mod timer;
 mod rotation;
+mod solver;
+mod judgement;
+mod levels;
 
+pub use judgement::PolyominoRotation;
+
+use crate::games::{
+    clock,
+    recording::{Input, Recording},
+    rng::{random_seed, Rng},
+    settings,
+    share::Puzzle,
+};
 use serde::{Deserialize, Serialize};
+use solver::Par;
 use tile::Direction;
 use wasm_bindgen::prelude::*;
-use web_sys::{Element, Event, MouseEvent};
+use web_sys::{Element, Event, KeyboardEvent, MouseEvent};
 use web_sys::{Document, Window};
 use wasm_bindgen::JsCast;
 use lazy_static::lazy_static;
+use std::cell::Cell;
 use std::sync::Mutex;
 
 lazy_static! {
     static ref GAME_INSTANCE: Mutex<Option<MentalRotation>> = Mutex::new(None);
 }
 
+thread_local! {
+    // Undo and redo controls outlive each level's game, so are bound once
+    static HISTORY_CONTROLS_INSTALLED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A tile's index and its state before a move, to put back on undo or redo.
+type HistoryEntry = (usize, tile::Tile);
+
 #[wasm_bindgen]
 #[derive(Clone, Serialize, Deserialize)]
 pub struct MentalRotation {
@@ -27,10 +49,46 @@ pub struct MentalRotation {
     grid_size: usize,
     start_pos: (usize, usize),
     end_pos: (usize, usize),
+    /// Sides of the grid the rocket and earth sit beyond; the last arrow of
+    /// the route points out through `end_side`.
+    #[serde(default = "default_start_side")]
+    start_side: Direction,
+    #[serde(default = "default_end_side")]
+    end_side: Direction,
     moves: usize,
+    /// Moves taken back; each undo also counts as a move.
+    #[serde(default)]
+    undos: usize,
+    /// Tile moves that can be undone, most recent last.
+    #[serde(default)]
+    undo_stack: Vec<HistoryEntry>,
+    /// Undone moves that can be made again, most recently undone last.
+    #[serde(default)]
+    redo_stack: Vec<HistoryEntry>,
+    /// Fewest clicks that solve the level, if the solver could tell.
+    #[serde(default)]
+    par: Option<usize>,
+    /// The solver gave up on the level, so `par` only bounds it from below.
+    #[serde(default)]
+    par_is_bound: bool,
     time_remaining: u32,
+    /// Played without a time limit, as the player's settings chose.
+    #[serde(default)]
+    untimed: bool,
+    /// Game time when the level began, for timing untimed levels.
+    #[serde(skip, default = "clock::now")]
+    started_at: f64,
     #[serde(skip)]
     last_click_time: f64,
+    // Seeds for this level and the one after it, and the session's input log
+    #[serde(default)]
+    seed: u32,
+    #[serde(default)]
+    next_seed: u32,
+    #[serde(default)]
+    recording: Recording,
+    #[serde(skip)]
+    replaying: bool,
 }
 
 #[wasm_bindgen]
@@ -45,46 +103,101 @@ impl MentalRotation {
         }
         
         // Otherwise create a new game
-        let grid_size = level; // Keep the original grid size calculation
+        Self::with_seed(level, random_seed())
+    }
+
+    fn with_seed(level: usize, seed: u32) -> Self {
+        let stage = levels::stage(level);
         
         // Use proper tuple destructuring to get all values from generate_level
-        let (tiles, solution_path_tiles, start_pos, end_pos) = level_generator::generate_level(level);
+        let mut rng = Rng::new(seed);
+        let level_generator::Level { tiles, solution_path_tiles, start_pos, end_pos, start_side, end_side, par } =
+            level_generator::generate_level(level, &mut rng);
         let initial_tiles = tiles.clone(); // Store initial configuration
+        let (par, par_is_bound) = match par {
+            Par::Exact(clicks) => (Some(clicks), false),
+            Par::AtLeast(clicks) => (Some(clicks), true),
+            Par::Unsolvable => (None, false),
+        };
+        let time_limit = settings::time_limit('m', stage.seconds, level);
         
         Self {
             level,
             tiles,
             initial_tiles,
             solution_path_tiles,
-            grid_size,
+            grid_size: stage.size,
             start_pos,
             end_pos,
+            start_side,
+            end_side,
             moves: 0,
-            time_remaining: 180,
+            undos: 0,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            par,
+            par_is_bound,
+            time_remaining: time_limit.unwrap_or(0),
+            untimed: time_limit.is_none(),
+            started_at: clock::now(),
             last_click_time: 0.0,
+            seed,
+            next_seed: rng.next_u32(),
+            recording: Recording::new('m', seed, level),
+            replaying: false,
         }
     }
 
-    fn get_arrow_classes(tile: &tile::Tile) -> String {
-        let rotation_class = match tile.rotation {
-            90 => "pointing-down",
-            180 => "pointing-left", 
-            270 => "pointing-up",
-            _ => "pointing-right",
-        };
-        
-        if tile.reversed {
-            format!("arrow {rotation_class} flipped")
-        } else {
-            format!("arrow {rotation_class}")
-        }
+    /// Builds the game for `level` that follows this one, continuing the
+    /// session's seed chain and input log.
+    fn successor(&self, level: usize) -> Self {
+        let mut next = Self::with_seed(level, self.next_seed);
+        next.recording = self.recording.clone();
+        next.replaying = self.replaying;
+        next
+    }
+
+    fn get_arrow_classes(tile: &tile::Tile, index: usize) -> String {
+        format!("arrow {}", tile.arrow(index).class_name())
     }
 
     pub fn handle_click(&mut self, event: &MouseEvent, tile_idx: usize) {
-        if event.button() == 0 {
-            self.rotate_tile(tile_idx);
-        } else if event.button() == 2 {
-            self.reverse_tile(tile_idx);
+        let input = match event.button() {
+            0 => Input::Rotate(tile_idx),
+            2 => Input::Reverse(tile_idx),
+            _ => return,
+        };
+        self.record_input(input);
+        self.apply_input(input);
+    }
+
+    fn apply_input(&mut self, input: Input) {
+        match input {
+            Input::Rotate(tile_idx) | Input::Reverse(tile_idx) => {
+                let before = self.tiles.get(tile_idx).cloned();
+                if input == Input::Rotate(tile_idx) {
+                    self.rotate_tile(tile_idx);
+                } else {
+                    self.reverse_tile(tile_idx);
+                }
+                // Blocked rotations change nothing, so leave nothing to undo
+                if let Some(before) = before.filter(|before| self.tiles.get(tile_idx) != Some(before)) {
+                    self.undo_stack.push((tile_idx, before));
+                    self.redo_stack.clear();
+                }
+            }
+            Input::Undo => {
+                if !Self::restore(&mut self.tiles, &mut self.undo_stack, &mut self.redo_stack) {
+                    return;
+                }
+                self.undos += 1;
+            }
+            Input::Redo => {
+                if !Self::restore(&mut self.tiles, &mut self.redo_stack, &mut self.undo_stack) {
+                    return;
+                }
+            }
+            _ => return,
         }
         self.moves += 1;
         
@@ -93,6 +206,31 @@ impl MentalRotation {
             self.trigger_win_animation();
         }
     }
+
+    /// Puts back the tile state on top of `from`, keeping the one it
+    /// replaces on `to` so the step can be taken back in turn. Moves come
+    /// off the stacks in the reverse of the order they went on, so the
+    /// state put back always still fits the board.
+    fn restore(tiles: &mut [tile::Tile], from: &mut Vec<HistoryEntry>, to: &mut Vec<HistoryEntry>) -> bool {
+        let Some((tile_idx, tile)) = from.pop() else {
+            return false;
+        };
+        let Some(current) = tiles.get_mut(tile_idx) else {
+            return false;
+        };
+        to.push((tile_idx, std::mem::replace(current, tile)));
+        true
+    }
+
+    fn record_input(&mut self, input: Input) {
+        if self.replaying {
+            return;
+        }
+        self.recording.record(input);
+        if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+            self.recording.publish(&document);
+        }
+    }
     
     fn rotate_tile(&mut self, tile_idx: usize) {
         // Check if rotation would cause a collision or go out of bounds
@@ -107,36 +245,24 @@ impl MentalRotation {
 
     // Helper function to check if a tile rotation would be valid
     fn is_valid_rotation(&self, tile_idx: usize) -> bool {
-        if let Some(tile) = self.tiles.get(tile_idx) {
-            // Create a copy of the tile with the proposed rotation
-            let mut rotated_tile = tile.clone();
-            rotated_tile.rotate();
-            
-            // Get the proposed rotated coordinates
-            let rotated_coords = rotation::rotate_coordinates(&tile.cells, 90);
-            
-            // Check for grid boundaries
-            for &(x, y) in &rotated_coords {
-                if x >= self.grid_size || y >= self.grid_size {
-                    return false; // Out of bounds
-                }
-            }
-            
-            // Check for collision with other tiles
-            for (other_idx, other_tile) in self.tiles.iter().enumerate() {
-                if other_idx != tile_idx { // Don't check against itself
-                    for &coord in &rotated_coords {
-                        if other_tile.cells.contains(&coord) {
-                            return false; // Collision with another tile
-                        }
-                    }
-                }
-            }
-            
-            return true; // No collisions or bounds issues
+        let Some(tile) = self.tiles.get(tile_idx) else {
+            return false; // Default to false if tile doesn't exist
+        };
+        
+        // Get the proposed rotated coordinates
+        let Some(rotated_coords) = rotation::rotate_coordinates_for_tile(tile) else {
+            return false; // Off the top or left of the grid
+        };
+        
+        // Check for grid boundaries
+        if rotated_coords.iter().any(|&(x, y)| x >= self.grid_size || y >= self.grid_size) {
+            return false; // Out of bounds
         }
         
-        false // Default to false if tile doesn't exist
+        // Check for collision with other tiles
+        !self.tiles.iter().enumerate().any(|(other_idx, other_tile)| {
+            other_idx != tile_idx && rotated_coords.iter().any(|coord| other_tile.cells.contains(coord))
+        })
     }
 
     fn reverse_tile(&mut self, tile_idx: usize) {
@@ -145,24 +271,15 @@ impl MentalRotation {
         }
     }
 
+    /// The arrow on `pos`, if a tile covers it.
+    fn arrow_at(&self, pos: (usize, usize)) -> Option<Direction> {
+        self.tiles.iter().find_map(|tile| tile.arrow_at(pos))
+    }
+
+    /// Won when the arrows lead cell by cell from the rocket to the earth,
+    /// whichever tiles and cells make up the route.
     fn check_win(&self) -> bool {
-        // A winning position is when all tiles in the path create a continuous path
-        // from start to end with arrows correctly connected
-        
-        // First, check that every tile in the path has the correct effective direction
-        for &tile_idx in &self.solution_path_tiles {
-            if let Some(tile) = self.tiles.get(tile_idx) {
-                // Check the effective direction is East (there may be multiple ways to achieve this)
-                if tile.get_effective_direction() != Direction::East {
-                    return false;
-                }
-            } else {
-                return false;
-            }
-        }
-        
-        // All solution tiles have the correct effective direction
-        true
+        solver::connects(|pos| self.arrow_at(pos), self.grid_size, self.start_pos, self.end_pos, self.end_side)
     }
 
     fn trigger_win_animation(&self) {
@@ -175,9 +292,14 @@ impl MentalRotation {
                 if let Some(rocket) = document.query_selector(".rocket").ok().flatten() {
                     let _ = rocket.class_list().add_1("moving");
                 }
+                Puzzle::new('m', self.seed, self.level)
+                    .solved(self.moves, self.elapsed_seconds())
+                    .publish(&document);
+                self.show_score(&document);
                 
                 // Progress to next level after animation
                 let next_level = self.level + 1;
+                let next_game = self.successor(next_level);
                 let closure = Closure::once_into_js(move || {
                     if let Some(window) = web_sys::window() {
                         if let Some(document) = window.document() {
@@ -186,9 +308,6 @@ impl MentalRotation {
                                 let _ = container.class_list().remove_1("animating");
                             }
                             
-                            // Create new game instance first
-                            let next_game = MentalRotation::new(next_level);
-                            
                             // Update game instance
                             if let Ok(mut lock) = GAME_INSTANCE.try_lock() {
                                 *lock = Some(next_game);
@@ -234,9 +353,22 @@ impl MentalRotation {
             level_display.set_text_content(Some(&format!("Level {}", self.level)));
         }
         
+        self.show_par(&document);
         self.setup_grid(&document)?;
-        self.setup_timer(&window)?;
+        if self.replaying {
+            // Replayed sessions carry their own timeouts
+            if let Some(handle) = unsafe { timer::TIMER_HANDLE } {
+                window.clear_interval_with_handle(handle);
+                unsafe { timer::TIMER_HANDLE = None; }
+            }
+        } else {
+            clock::install("mental_rotation_paused")?;
+            settings::install('m')?;
+            self.setup_timer(&window)?;
+            self.recording.publish(&document);
+        }
         self.setup_reset_button(&document)?;
+        Self::setup_history_controls(&document)?;
         
         // Update game instance last
         if let Ok(mut lock) = GAME_INSTANCE.try_lock() {
@@ -256,8 +388,8 @@ impl MentalRotation {
             grid.remove_child(&child)?;
         }
         
-        // Use the stored grid_size directly (which should match level)
-        grid.set_attribute("style", &format!("grid-template-columns: repeat({}, 3rem)", self.grid_size))?;
+        // The page's CSS sizes the columns to fit the screen
+        grid.set_attribute("style", &format!("--columns: {}", self.grid_size))?;
         
         // Add contextmenu prevention using closure
         let context_callback = Closure::wrap(Box::new(|e: Event| {
@@ -275,22 +407,7 @@ impl MentalRotation {
         for y in 0..self.grid_size {
             for x in 0..self.grid_size {
                 let cell = document.create_element("div")?;
-                cell.set_class_name("cell");
-                
-                // Handle tiles
-                for (tile_idx, tile) in self.tiles.iter().enumerate() {
-                    if tile.cells.contains(&(x, y)) {
-                        cell.set_class_name("cell tile");
-                        cell.set_attribute("data-position", &format!("{x}{y}"))?;
-                        let arrow = document.create_element("span")?;
-                        arrow.set_class_name(&MentalRotation::get_arrow_classes(tile));
-                        arrow.set_text_content(Some("➔"));
-                        cell.append_child(&arrow)?;
-                        cell.set_attribute("data-tile", &tile_idx.to_string())?;
-                        break;
-                    }
-                }
-                
+                self.fill_cell(document, &cell, (x, y))?;
                 grid.append_child(&cell)?;
             }
         }
@@ -306,30 +423,24 @@ impl MentalRotation {
         // Add rocket and earth at correct positions
         let grid_container = document.query_selector(".grid-container")?.unwrap();
         
-        // Create rocket element
+        // Create rocket element, beside its cell on whichever side of the grid
+        // it launches from, and set to fly to the earth
+        let (left, top) = self.marker_position(self.start_pos, self.start_side);
+        let (earth_left, earth_top) = self.marker_position(self.end_pos, self.end_side);
         let rocket = document.create_element("span")?;
         rocket.set_class_name("rocket");
         rocket.set_text_content(Some("🚀"));
-        
-        // Adjust rocket vertical position to align with start_pos if necessary
-        if self.grid_size > 1 {
-            let start_y_percent = (self.start_pos.1 as f64 / (self.grid_size - 1) as f64) * 100.0;
-            rocket.set_attribute("style", &format!("top: {}%;", start_y_percent))?;
-        }
-        
+        rocket.set_attribute(
+            "style",
+            &format!("left: {left}; top: {top}; --to-left: {earth_left}; --to-top: {earth_top};"),
+        )?;
         grid_container.append_child(&rocket)?;
 
         // Create earth element
         let earth = document.create_element("span")?;
         earth.set_class_name("earth");
         earth.set_text_content(Some("🌍"));
-        
-        // Adjust earth vertical position to align with end_pos if necessary
-        if self.grid_size > 1 {
-            let end_y_percent = (self.end_pos.1 as f64 / (self.grid_size - 1) as f64) * 100.0;
-            earth.set_attribute("style", &format!("top: {}%;", end_y_percent))?;
-        }
-        
+        earth.set_attribute("style", &format!("left: {earth_left}; top: {earth_top};"))?;
         grid_container.append_child(&earth)?;
 
         // Create a click handler that doesn't capture the document reference
@@ -358,6 +469,13 @@ impl MentalRotation {
                 if let Ok(idx) = tile_idx.parse::<usize>() {
                     if let Ok(mut lock) = GAME_INSTANCE.try_lock() {
                         if let Some(mut game) = lock.take() {
+                            // Clicks are ignored while a recorded session plays
+                            // or the game is paused
+                            if game.replaying || !clock::is_running() {
+                                *lock = Some(game);
+                                return;
+                            }
+
                             // Debounce using last click time
                             let now = js_sys::Date::now();
                             if now - game.last_click_time < 100.0 {
@@ -367,20 +485,7 @@ impl MentalRotation {
                             game.last_click_time = now;
 
                             game.handle_click(&event, idx);
-                            
-                            // Get document from window inside the closure
-                            if let Some(window) = web_sys::window() {
-                                if let Some(document) = window.document() {
-                                    if let Some(tile) = game.tiles.get(idx) {
-                                        for &(x, y) in &tile.cells {
-                                            let selector = format!(".cell[data-position='{x}{y}'] .arrow");
-                                            if let Some(arrow) = document.query_selector(&selector).ok().flatten() {
-                                                arrow.set_class_name(&MentalRotation::get_arrow_classes(tile));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
+                            game.refresh_tiles();
                             
                             game.save_state();
                             *lock = Some(game);
@@ -400,8 +505,119 @@ impl MentalRotation {
         Ok(())
     }
 
+    /// Where the rocket or earth beside `pos` on the `side` of the grid sits
+    /// in the grid container, as CSS `left` and `top` values.
+    fn marker_position(&self, (x, y): (usize, usize), side: Direction) -> (String, String) {
+        // The grid sits inside 3rem of padding, and markers are centred in it
+        let along = |i: usize| {
+            let share = (i as f64 + 0.5) / self.grid_size as f64;
+            format!("calc(3rem + (100% - 6rem) * {share})")
+        };
+        let near = "1.5rem".to_string();
+        let far = "calc(100% - 1.5rem)".to_string();
+        match side {
+            Direction::North => (along(x), near),
+            Direction::South => (along(x), far),
+            Direction::West => (near, along(y)),
+            _ => (far, along(y)),
+        }
+    }
+
+    /// Draws whichever tile covers `pos` into its grid cell, or leaves the
+    /// cell empty.
+    fn fill_cell(&self, document: &Document, cell: &Element, (x, y): (usize, usize)) -> Result<(), JsValue> {
+        cell.set_class_name("cell");
+        cell.set_text_content(None);
+        cell.remove_attribute("data-position")?;
+        cell.remove_attribute("data-tile")?;
+        
+        // Handle tiles
+        for (tile_idx, tile) in self.tiles.iter().enumerate() {
+            if let Some(index) = tile.cells.iter().position(|&c| c == (x, y)) {
+                cell.set_class_name("cell tile");
+                cell.set_attribute("data-position", &format!("{x}{y}"))?;
+                let arrow = document.create_element("span")?;
+                arrow.set_class_name(&MentalRotation::get_arrow_classes(tile, index));
+                arrow.set_text_content(Some("➔"));
+                cell.append_child(&arrow)?;
+                cell.set_attribute("data-tile", &tile_idx.to_string())?;
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Redraws the grid's cells after a tile has been rotated or reversed.
+    /// Rotating moves a tile's cells, so every cell is redrawn rather than
+    /// just the tile's own.
+    fn refresh_tiles(&self) {
+        // Get document from window rather than holding on to one
+        if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+            if let Some(grid) = document.get_element_by_id("grid") {
+                let cells = grid.children();
+                for y in 0..self.grid_size {
+                    for x in 0..self.grid_size {
+                        if let Some(cell) = cells.item((y * self.grid_size + x) as u32) {
+                            let _ = self.fill_cell(&document, &cell, (x, y));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Shows the fewest clicks the level can be solved in, and hides the
+    /// last level's score.
+    fn show_par(&self, document: &Document) {
+        if let Some(par_display) = document.query_selector(".par").ok().flatten() {
+            let text = match self.par {
+                Some(par) if self.par_is_bound => format!("Par: {par}+"),
+                Some(par) => format!("Par: {par}"),
+                None => "Par: ?".to_string(),
+            };
+            par_display.set_text_content(Some(&text));
+        }
+        if let Some(score) = document.get_element_by_id("score") {
+            let _ = score.set_attribute("hidden", "");
+        }
+    }
+
+    /// Scores the moves the level took against its par.
+    fn show_score(&self, document: &Document) {
+        let Some(score) = document.get_element_by_id("score") else {
+            return;
+        };
+        let text = match self.par {
+            Some(par) if self.par_is_bound => {
+                format!("Solved in {} moves, par at least {par}", self.moves)
+            }
+            Some(par) if self.moves > par => {
+                format!("Solved in {} moves, {} over par ({par})", self.moves, self.moves - par)
+            }
+            Some(_) => format!("Solved in {} moves: par!", self.moves),
+            None => format!("Solved in {} moves", self.moves),
+        };
+        let text = match self.undos {
+            0 => text,
+            1 => format!("{text}, with 1 undo"),
+            undos => format!("{text}, with {undos} undos"),
+        };
+        score.set_text_content(Some(&text));
+        let _ = score.remove_attribute("hidden");
+    }
+
     fn setup_timer(&self, window: &Window) -> Result<(), JsValue> {
-        timer::setup_timer(window, self.time_remaining)
+        timer::setup_timer(window, (!self.untimed).then_some(self.time_remaining))
+    }
+
+    /// Seconds spent on the level so far.
+    fn elapsed_seconds(&self) -> u32 {
+        if self.untimed {
+            ((clock::now() - self.started_at) / 1000.0) as u32
+        } else {
+            settings::time_limit('m', levels::stage(self.level).seconds, self.level)
+                .map_or(0, |limit| limit.saturating_sub(self.time_remaining))
+        }
     }
 
     fn setup_reset_button(&self, document: &Document) -> Result<(), JsValue> {
@@ -410,8 +626,16 @@ impl MentalRotation {
             let reset_callback = Closure::wrap(Box::new(move |_: Event| {
                 if let Ok(mut lock) = GAME_INSTANCE.try_lock() {
                     if let Some(mut game) = lock.take() {
+                        if game.replaying {
+                            *lock = Some(game);
+                            return;
+                        }
+                        game.record_input(Input::Reset);
+
                         // Reset tiles to initial configuration without affecting moves or timer
-                        game.tiles = game.initial_tiles.clone();
+                        game.tiles.clone_from(&game.initial_tiles);
+                        game.undo_stack.clear();
+                        game.redo_stack.clear();
                         
                         // Update game instance first
                         *lock = Some(game.clone());
@@ -437,7 +661,67 @@ impl MentalRotation {
         Ok(())
     }
 
+    /// Binds the undo and redo buttons and their shortcuts: Ctrl+Z to undo,
+    /// Ctrl+Y or Ctrl+Shift+Z to redo (Cmd on a Mac).
+    fn setup_history_controls(document: &Document) -> Result<(), JsValue> {
+        if HISTORY_CONTROLS_INSTALLED.with(|installed| installed.replace(true)) {
+            return Ok(());
+        }
+        
+        for (id, input) in [("undo", Input::Undo), ("redo", Input::Redo)].iter().copied() {
+            if let Some(button) = document.get_element_by_id(id) {
+                let callback = Closure::wrap(Box::new(move |_: Event| {
+                    MentalRotation::undo_or_redo(input);
+                }) as Box<dyn FnMut(Event)>);
+                button.add_event_listener_with_callback("click", callback.as_ref().unchecked_ref())?;
+                callback.forget();
+            }
+        }
+        
+        let key_callback = Closure::wrap(Box::new(move |event: KeyboardEvent| {
+            if !(event.ctrl_key() || event.meta_key()) {
+                return;
+            }
+            let input = match event.key().to_lowercase().as_str() {
+                "z" if event.shift_key() => Input::Redo,
+                "z" => Input::Undo,
+                "y" => Input::Redo,
+                _ => return,
+            };
+            event.prevent_default();
+            MentalRotation::undo_or_redo(input);
+        }) as Box<dyn FnMut(KeyboardEvent)>);
+        document.add_event_listener_with_callback("keydown", key_callback.as_ref().unchecked_ref())?;
+        key_callback.forget();
+        
+        Ok(())
+    }
+
+    /// Undoes or redoes a move for the buttons and shortcuts.
+    fn undo_or_redo(input: Input) {
+        let Ok(mut lock) = GAME_INSTANCE.try_lock() else {
+            return;
+        };
+        let Some(game) = lock.as_mut() else {
+            return;
+        };
+        // Ignored while a recorded session plays, the game is paused, or
+        // there is nothing to take back or make again
+        let pending = if input == Input::Undo { &game.undo_stack } else { &game.redo_stack };
+        if game.replaying || !clock::is_running() || pending.is_empty() {
+            return;
+        }
+        game.record_input(input);
+        game.apply_input(input);
+        game.refresh_tiles();
+        game.save_state();
+    }
+
     fn save_state(&self) {
+        // A replayed session must not overwrite the player's own progress
+        if self.replaying {
+            return;
+        }
         if let Some(window) = web_sys::window() {
             if let Some(storage) = window.local_storage().ok().flatten() {
                 if let Ok(json_state) = serde_json::to_string(self) {
@@ -450,8 +734,10 @@ impl MentalRotation {
     pub fn clear_game_state(&self) {
         // Clear any saved state to prevent loading it immediately again
         if let Some(window) = web_sys::window() {
-            if let Some(storage) = window.local_storage().ok().flatten() {
-                let _ = storage.remove_item("mental_rotation_state");
+            if !self.replaying {
+                if let Some(storage) = window.local_storage().ok().flatten() {
+                    let _ = storage.remove_item("mental_rotation_state");
+                }
             }
             
             // Clear any existing timer to prevent multiple timers running simultaneously
@@ -468,6 +754,64 @@ impl MentalRotation {
     }
 }
 
+impl MentalRotation {
+    /// Starts a session on a level someone shared, from its seed and level.
+    pub(crate) fn play_puzzle(puzzle: &Puzzle) -> Result<(), JsValue> {
+        if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+            puzzle.announce(&document);
+        }
+        Self::with_seed(puzzle.level, puzzle.seed).start()
+    }
+
+    /// Regenerates the recorded session's first level and re-drives it from the log.
+    pub(crate) fn replay(recording: &Recording) -> Result<(), JsValue> {
+        let mut game = Self::with_seed(recording.level, recording.seed);
+        game.replaying = true;
+        game.start()?;
+
+        recording.play(|input| {
+            let Ok(mut lock) = GAME_INSTANCE.try_lock() else {
+                return;
+            };
+            let Some(mut game) = lock.take() else {
+                return;
+            };
+            match input {
+                Input::Rotate(_) | Input::Reverse(_) | Input::Undo | Input::Redo => {
+                    game.apply_input(input);
+                    game.refresh_tiles();
+                    *lock = Some(game);
+                }
+                Input::Timeout => {
+                    // start() takes the lock itself
+                    drop(lock);
+                    let _ = game.successor(game.level).start();
+                }
+                Input::Reset => {
+                    game.tiles.clone_from(&game.initial_tiles);
+                    game.undo_stack.clear();
+                    game.redo_stack.clear();
+                    if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+                        let _ = game.setup_grid(&document);
+                    }
+                    *lock = Some(game);
+                }
+                _ => *lock = Some(game),
+            }
+        })
+    }
+}
+
+// Games saved before the rocket and earth could sit on any side had them
+// left and right of the grid
+fn default_start_side() -> Direction {
+    Direction::West
+}
+
+fn default_end_side() -> Direction {
+    Direction::East
+}
+
 fn load_saved_game_state() -> Option<MentalRotation> {
     if let Some(window) = web_sys::window() {
         if let Some(storage) = window.local_storage().ok().flatten() {
@@ -1,39 +1,25 @@
 use super::tile::Tile;
 
-pub fn rotate_coordinates(cells: &[(usize, usize)], rotation: i32) -> Vec<(usize, usize)> {
-    // Convert coordinates to signed ints for safe arithmetic
-    let cells_i32: Vec<(i32, i32)> = cells.iter()
-        .map(|&(x, y)| (x as i32, y as i32))
-        .collect();
-    
-    // Find bounding box
-    let min_x = cells_i32.iter().map(|&(x, _)| x).min().unwrap_or(0);
-    let min_y = cells_i32.iter().map(|&(_, y)| y).min().unwrap_or(0);
-
-    // Calculate center of rotation (use top-left as anchor)
-    let center_x = min_x;
-    let center_y = min_y;
+/// Turns `cells` clockwise on screen by `rotation` degrees (a multiple of
+/// 90) about `pivot`. Returns `None` if a cell would be pushed past the
+/// grid's top or left edge; the caller checks the other two.
+pub fn rotate_coordinates(cells: &[(usize, usize)], pivot: (usize, usize), rotation: i32) -> Option<Vec<(usize, usize)>> {
+    let quarter_turns = rotation.rem_euclid(360) / 90;
+    let (pivot_x, pivot_y) = (pivot.0 as i64, pivot.1 as i64);
 
-    // Invert angle for clockwise rotation
-    let angle = -f64::from(rotation).to_radians();
-    let sin = angle.sin();
-    let cos = angle.cos();
-
-    cells_i32.iter()
+    cells.iter()
         .map(|&(x, y)| {
-            // Translate to origin
-            let dx = x - center_x;
-            let dy = y - center_y;
+            // Translate to the pivot
+            let (mut dx, mut dy) = (x as i64 - pivot_x, y as i64 - pivot_y);
             
-            // Rotate clockwise
-            let rx = (f64::from(dx) * cos - f64::from(dy) * sin).round() as i32;
-            let ry = (f64::from(dx) * sin + f64::from(dy) * cos).round() as i32;
+            // With y growing down the screen, (dx, dy) -> (-dy, dx) is clockwise
+            for _ in 0..quarter_turns {
+                (dx, dy) = (-dy, dx);
+            }
             
             // Translate back
-            let new_x = (rx + center_x) as usize;
-            let new_y = (ry + center_y) as usize;
-            
-            (new_x, new_y)
+            let (new_x, new_y) = (pivot_x + dx, pivot_y + dy);
+            (new_x >= 0 && new_y >= 0).then_some((new_x as usize, new_y as usize))
         })
         .collect()
 }
@@ -45,5 +31,5 @@ pub fn rotate_coordinates_for_tile(tile: &Tile) -> Option<Vec<(usize, usize)>> {
     }
     
     // Simulate the next rotation (90° clockwise)
-    Some(rotate_coordinates(&tile.cells, 90))
+    rotate_coordinates(&tile.cells, tile.pivot(), 90)
 }
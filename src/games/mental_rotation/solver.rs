@@ -0,0 +1,231 @@
+//! Finding the fewest clicks that solve a level, by breadth-first search
+//! over the orientation and reversal of every tile.
+
+use super::{
+    rotation,
+    tile::{Direction, Tile},
+};
+use std::collections::{HashSet, VecDeque};
+
+/// Arrangements explored before a level is given up on as too big to solve.
+const MAX_STATES: usize = 100_000;
+
+/// Follows the arrows cell by cell from `start`. The route is complete when
+/// they lead to `end` and point out of the grid there, towards `exit`.
+pub fn connects(
+    arrow_at: impl Fn((usize, usize)) -> Option<Direction>,
+    grid_size: usize,
+    start: (usize, usize),
+    end: (usize, usize),
+    exit: Direction,
+) -> bool {
+    let mut pos = start;
+    // A route longer than the grid has cells must be going round in circles
+    for _ in 0..grid_size * grid_size {
+        let Some(direction) = arrow_at(pos) else {
+            return false;
+        };
+        if pos == end && direction == exit {
+            return true;
+        }
+        match direction.step(pos, grid_size) {
+            Some(next) => pos = next,
+            None => return false,
+        }
+    }
+    false
+}
+
+/// Each tile's four orientations, unreversed, in the order clicks turn it
+/// through them. An orientation off the grid is `None`, and can't be reached.
+fn orientations(tile: &Tile, grid_size: usize) -> [Option<Tile>; 4] {
+    let mut turned = Tile {
+        reversed: false,
+        ..tile.clone()
+    };
+    let mut all = [Some(turned.clone()), None, None, None];
+    for orientation in all.iter_mut().skip(1) {
+        match rotation::rotate_coordinates_for_tile(&turned) {
+            Some(cells) if cells.iter().all(|&(x, y)| x < grid_size && y < grid_size) => {
+                turned.rotate();
+                *orientation = Some(turned.clone());
+            }
+            _ => break,
+        }
+    }
+    all
+}
+
+/// Bits each tile takes in a packed arrangement: two for how many clicks
+/// it has been turned on from where it started, one for whether it is
+/// reversed.
+const TILE_BITS: usize = 3;
+
+/// Every tile's orientation and reversal, packed into one number so that
+/// arrangements are cheap to copy and remember.
+type State = u128;
+
+fn tile_state(state: State, idx: usize) -> (usize, bool) {
+    let bits = (state >> (idx * TILE_BITS)) as usize;
+    (bits & 0b11, bits & 0b100 != 0)
+}
+
+fn with_tile_state(state: State, idx: usize, (orientation, reversed): (usize, bool)) -> State {
+    let bits = (orientation | usize::from(reversed) << 2) as State;
+    let shift = idx * TILE_BITS;
+    state & !(0b111 << shift) | bits << shift
+}
+
+/// What the search found out about a level's par.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Par {
+    /// The fewest clicks that solve the level.
+    Exact(usize),
+    /// The search gave up before finding a solution, having ruled out any
+    /// with fewer clicks than this.
+    AtLeast(usize),
+    /// No arrangement the clicks can reach solves the level.
+    Unsolvable,
+}
+
+/// Fewest clicks that take `tiles` to an arrangement whose arrows lead from
+/// `start` to `end` and out towards `exit`.
+pub fn par(
+    tiles: &[Tile],
+    grid_size: usize,
+    start: (usize, usize),
+    end: (usize, usize),
+    exit: Direction,
+) -> Par {
+    if tiles.len() * TILE_BITS > State::BITS as usize {
+        return Par::AtLeast(0);
+    }
+    let orientations: Vec<_> = tiles
+        .iter()
+        .map(|tile| orientations(tile, grid_size))
+        .collect();
+    let initial = tiles.iter().enumerate().fold(0, |state, (idx, tile)| {
+        with_tile_state(state, idx, (0, tile.reversed))
+    });
+    let tile = |state: State, idx: usize| orientations[idx][tile_state(state, idx).0].as_ref();
+
+    // Which tile covers each cell of the grid, row by row
+    let owners = |state: State| {
+        let mut owners = vec![None; grid_size * grid_size];
+        for idx in 0..tiles.len() {
+            for &(x, y) in tile(state, idx).map_or(&[][..], |t| &t.cells) {
+                owners[y * grid_size + x] = Some(idx);
+            }
+        }
+        owners
+    };
+    let solved = |state: State, owners: &[Option<usize>]| {
+        let arrow_at = |(x, y): (usize, usize)| {
+            let idx = owners[y * grid_size + x]?;
+            let tile = tile(state, idx)?;
+            let index = tile.cells.iter().position(|&cell| cell == (x, y))?;
+            Some(tile.arrow_when(index, tile_state(state, idx).1))
+        };
+        connects(arrow_at, grid_size, start, end, exit)
+    };
+
+    let mut seen = HashSet::from([initial]);
+    let mut queue = VecDeque::from([(initial, 0)]);
+    while let Some((state, clicks)) = queue.pop_front() {
+        let owners = owners(state);
+        if solved(state, &owners) {
+            return Par::Exact(clicks);
+        }
+        for (idx, turns) in orientations.iter().enumerate() {
+            // A left click turns the tile on if it stays on the grid and
+            // clear of the others; a right click reverses it
+            let (orientation, reversed) = tile_state(state, idx);
+            let turn = (orientation + 1) % 4;
+            let can_turn = turns[turn].as_ref().is_some_and(|turned| {
+                turned
+                    .cells
+                    .iter()
+                    .all(|&(x, y)| owners[y * grid_size + x].is_none_or(|other| other == idx))
+            });
+
+            for next in [
+                can_turn.then_some((turn, reversed)),
+                Some((orientation, !reversed)),
+            ]
+            .iter()
+            .flatten()
+            {
+                // Breadth first, so nothing shallower than this state is left
+                if seen.len() >= MAX_STATES {
+                    return Par::AtLeast(clicks);
+                }
+                let next = with_tile_state(state, idx, *next);
+                if seen.insert(next) {
+                    queue.push_back((next, clicks + 1));
+                }
+            }
+        }
+    }
+    Par::Unsolvable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Direction::{East, North, West};
+
+    fn tile(cells: &[(usize, usize)], arrows: &[Direction], reversed: bool) -> Tile {
+        Tile {
+            cells: cells.to_vec(),
+            arrows: arrows.to_vec(),
+            rotation: 0,
+            reversed,
+        }
+    }
+
+    #[test]
+    fn a_solved_level_needs_no_clicks() {
+        let tiles = [tile(&[(0, 1), (1, 1), (2, 1)], &[East; 3], false)];
+        assert_eq!(par(&tiles, 3, (0, 1), (2, 1), East), Par::Exact(0));
+    }
+
+    #[test]
+    fn a_turn_counts_one_click() {
+        let tiles = [tile(&[(1, 2), (1, 1), (1, 0)], &[North; 3], false)];
+        assert_eq!(par(&tiles, 3, (0, 1), (2, 1), East), Par::Exact(1));
+    }
+
+    #[test]
+    fn a_reversal_counts_one_click() {
+        let tiles = [tile(&[(2, 1), (1, 1), (0, 1)], &[West; 3], false)];
+        assert_eq!(par(&tiles, 3, (0, 1), (2, 1), East), Par::Exact(1));
+    }
+
+    #[test]
+    fn a_reversed_bend_runs_its_route_backwards() {
+        // Right from the left edge, then up and out of the top; reversed, down
+        // from the top, then left and out of the left edge
+        let (cells, arrows) = ([(0, 1), (1, 1), (1, 0)], [East, North, North]);
+        let bend = |reversed| [tile(&cells, &arrows, reversed)];
+        assert_eq!(par(&bend(false), 3, (0, 1), (1, 0), North), Par::Exact(0));
+        assert_eq!(par(&bend(true), 3, (1, 0), (0, 1), West), Par::Exact(0));
+        assert_eq!(par(&bend(false), 3, (1, 0), (0, 1), West), Par::Exact(1));
+    }
+
+    #[test]
+    fn an_unreachable_end_has_no_par() {
+        assert_eq!(par(&[], 3, (0, 1), (2, 1), East), Par::Unsolvable);
+        let tiles = [tile(&[(0, 1), (1, 1)], &[East; 2], false)];
+        assert_eq!(par(&tiles, 3, (0, 1), (2, 1), East), Par::Unsolvable);
+    }
+
+    #[test]
+    fn a_search_too_big_to_finish_gives_a_lower_bound() {
+        // Sixteen single-cell tiles: every one can be reversed, giving more
+        // arrangements than the search will look at
+        let tiles: Vec<Tile> = (0..16).map(|i| tile(&[(i % 4, i / 4)], &[West], false)).collect();
+        assert!(matches!(par(&tiles, 4, (0, 0), (3, 3), East), Par::AtLeast(clicks) if clicks > 1));
+        let tiles: Vec<Tile> = (0..50).map(|i| tile(&[(i % 8, i / 8)], &[East], false)).collect();
+        assert_eq!(par(&tiles, 8, (0, 0), (7, 0), North), Par::AtLeast(0));
+    }
+}
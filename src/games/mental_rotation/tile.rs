@@ -1,3 +1,4 @@
+use super::rotation;
 use serde::{Serialize, Deserialize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -13,29 +14,35 @@ pub enum Direction {
 }
 
 impl Direction {
-    pub fn reversed(self) -> Self {
-        match self {
-            Direction::North => Direction::South,
-            Direction::South => Direction::North,
-            Direction::East => Direction::West,
-            Direction::West => Direction::East,
-            Direction::NorthEast => Direction::SouthWest,
-            Direction::NorthWest => Direction::SouthEast,
-            Direction::SouthEast => Direction::NorthWest,
-            Direction::SouthWest => Direction::NorthEast,
-        }
+    /// The neighbouring cell this direction points to from `(x, y)`, if it
+    /// is inside a `grid_size` square grid.
+    pub fn step(self, (x, y): (usize, usize), grid_size: usize) -> Option<(usize, usize)> {
+        let (dx, dy) = match self {
+            Direction::North => (0, -1),
+            Direction::South => (0, 1),
+            Direction::East => (1, 0),
+            Direction::West => (-1, 0),
+            Direction::NorthEast => (1, -1),
+            Direction::NorthWest => (-1, -1),
+            Direction::SouthEast => (1, 1),
+            Direction::SouthWest => (-1, 1),
+        };
+        let nx = x.checked_add_signed(dx)?;
+        let ny = y.checked_add_signed(dy)?;
+        (nx < grid_size && ny < grid_size).then_some((nx, ny))
     }
 
-    pub fn rotate_90(self) -> Self {
+    /// Class that turns an arrow glyph to point this way.
+    pub fn class_name(self) -> &'static str {
         match self {
-            Direction::North => Direction::East,
-            Direction::East => Direction::South,
-            Direction::South => Direction::West, 
-            Direction::West => Direction::North,
-            Direction::NorthEast => Direction::SouthEast,
-            Direction::SouthEast => Direction::SouthWest,
-            Direction::SouthWest => Direction::NorthWest,
-            Direction::NorthWest => Direction::NorthEast,
+            Direction::North => "pointing-up",
+            Direction::South => "pointing-down",
+            Direction::East => "pointing-right",
+            Direction::West => "pointing-left",
+            Direction::NorthEast => "pointing-northeast",
+            Direction::NorthWest => "pointing-northwest",
+            Direction::SouthEast => "pointing-southeast",
+            Direction::SouthWest => "pointing-southwest",
         }
     }
 
@@ -81,17 +88,43 @@ impl Direction {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Tile {
     pub cells: Vec<(usize, usize)>,
+    /// Arrow on each of `cells`, before rotation and reversal. Tiles saved
+    /// before arrows were per cell have none, and point east throughout.
+    #[serde(default)]
+    pub arrows: Vec<Direction>,
     pub rotation: i32,  // Degrees
     pub reversed: bool,
 }
 
 impl Tile {
+    /// The cell the tile turns about: the one nearest its middle, so the
+    /// tile stays roughly in place. Turning keeps every distance within the
+    /// tile, so this is the same cell in every orientation.
+    pub fn pivot(&self) -> (usize, usize) {
+        let n = self.cells.len() as i64;
+        let sum_x: i64 = self.cells.iter().map(|&(x, _)| x as i64).sum();
+        let sum_y: i64 = self.cells.iter().map(|&(_, y)| y as i64).sum();
+        
+        // Squared distance to the middle, scaled by n^2 to stay in integers
+        let distance = |&(x, y): &(usize, usize)| {
+            let dx = x as i64 * n - sum_x;
+            let dy = y as i64 * n - sum_y;
+            dx * dx + dy * dy
+        };
+        self.cells.iter().copied().min_by_key(distance).unwrap_or((0, 0))
+    }
+
+    /// Turns the tile 90° clockwise about its pivot, moving its cells as
+    /// well as its arrows. Whether the new cells are free and on the grid
+    /// is for the caller to check first.
     pub fn rotate(&mut self) {
-        self.rotation = (self.rotation + 90) % 360;
-        // Remove coordinate transformation - let CSS handle visual rotation
+        if let Some(cells) = rotation::rotate_coordinates_for_tile(self) {
+            self.cells = cells;
+            self.rotation = (self.rotation + 90) % 360;
+        }
     }
 
     pub fn reverse(&mut self) {
@@ -99,27 +132,24 @@ impl Tile {
         // Remove arrow reversal since we handle it in get_effective_direction
     }
 
-    pub fn get_effective_direction(&self) -> Direction {
-        // Calculate the effective direction based on rotation and reversal
-        // There are multiple combinations that can result in the same effective direction
-        
-        // Start with East as the base direction
-        let base_direction = Direction::East;
-        
-        // Apply rotation
-        let rotated = match self.rotation {
-            0 => base_direction,
-            90 => base_direction.rotate_90(),
-            180 => base_direction.rotate_90().rotate_90(),
-            270 => base_direction.rotate_90().rotate_90().rotate_90(),
-            _ => base_direction, // Default to East for invalid rotations
-        };
-        
-        // Apply reversal if needed
-        if self.reversed {
-            rotated.reversed()
-        } else {
-            rotated
-        }
+    /// Direction the arrow on the tile's `index`th cell points in, once the
+    /// tile's rotation and reversal are applied.
+    pub fn arrow(&self, index: usize) -> Direction {
+        self.arrow_when(index, self.reversed)
+    }
+
+    /// Direction the arrow on the `index`th cell points in at the tile's
+    /// rotation, reversed or not. Reversing runs the tile's route backwards:
+    /// each cell points back at the one before it, and the first cell points
+    /// away from the second, off the tile.
+    pub fn arrow_when(&self, index: usize, reversed: bool) -> Direction {
+        let source = if reversed { index.saturating_sub(1) } else { index };
+        let base = self.arrows.get(source).copied().unwrap_or(Direction::East);
+        Direction::from_rotation(base, self.rotation, reversed)
+    }
+
+    /// Direction of the arrow on `pos`, if the tile covers it.
+    pub fn arrow_at(&self, pos: (usize, usize)) -> Option<Direction> {
+        self.cells.iter().position(|&cell| cell == pos).map(|index| self.arrow(index))
     }
 }
@@ -2,11 +2,14 @@ use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::{Document, Window};
 use crate::games::mental_rotation::GAME_INSTANCE;
+use crate::games::{clock, recording::Input};
 
 // Make the timer handle public so it can be accessed from the main module
 pub static mut TIMER_HANDLE: Option<i32> = None;
 
-pub fn setup_timer(window: &Window, initial_seconds: u32) -> Result<(), JsValue> {
+/// Counts down from `initial_seconds`, or just shows that the level is
+/// untimed when there is no limit.
+pub fn setup_timer(window: &Window, initial_seconds: Option<u32>) -> Result<(), JsValue> {
     // Clear any existing timer
     if let Some(handle) = unsafe { TIMER_HANDLE } {
         window.clear_interval_with_handle(handle);
@@ -15,6 +18,10 @@ pub fn setup_timer(window: &Window, initial_seconds: u32) -> Result<(), JsValue>
     
     let document = window.document().unwrap();
     let timer_element = document.query_selector(".timer")?.unwrap();
+    let Some(initial_seconds) = initial_seconds else {
+        timer_element.set_text_content(Some("Untimed"));
+        return Ok(());
+    };
     
     // Format and set the initial time
     let mins = initial_seconds / 60;
@@ -23,6 +30,9 @@ pub fn setup_timer(window: &Window, initial_seconds: u32) -> Result<(), JsValue>
     
     // Create closure for the timer update
     let timer_callback = Closure::wrap(Box::new(move || {
+        if !clock::is_running() {
+            return;
+        }
         if let Some(window) = web_sys::window() {
             if let Some(document) = window.document() {
                 if let Some(timer_element) = document.query_selector(".timer").ok().flatten() {
@@ -48,14 +58,14 @@ pub fn setup_timer(window: &Window, initial_seconds: u32) -> Result<(), JsValue>
                                     unsafe { TIMER_HANDLE = None; }
                                 }
                                 
-                                let current_level = game.level;
+                                game.record_input(Input::Timeout);
+                                let new_game = game.successor(game.level);
                                 game.clear_game_state();
                                 *game_lock = None; // Clear the game instance before creating new one
                                 
-                                // Create and start new game with same level outside of the lock
+                                // Start new game with same level outside of the lock
                                 // Fix: Use closure.as_ref().unchecked_ref() to get proper type
                                 let timeout_callback = Closure::once(move || {
-                                    let new_game = crate::games::mental_rotation::MentalRotation::new(current_level);
                                     let _ = new_game.start();
                                 });
                                 
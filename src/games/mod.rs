@@ -1,3 +1,11 @@
 pub mod numeracy;
 pub mod perception;
 pub mod mental_rotation;
+pub mod cube_rotation;
+pub mod rng;
+pub mod clock;
+pub mod recording;
+pub mod schedule;
+pub mod settings;
+pub mod share;
+pub mod trials;
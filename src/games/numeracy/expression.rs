@@ -1,4 +1,4 @@
-use js_sys::Math;
+use crate::games::rng::Rng;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Expression {
@@ -7,18 +7,18 @@ pub struct Expression {
 }
 
 impl Expression {
-    pub fn new(level: u32) -> Self {
+    pub fn new(level: u32, rng: &mut Rng) -> Self {
         let ops = ['+', '-', '*', '/'];
         let denominators = [2, 4, 5, 8];
-        let use_decimals = level > 3 && Math::random() < 0.3;
+        let use_decimals = level > 3 && rng.random() < 0.3;
 
         let complexity = (f64::from(level) * 1.2).ceil() as i32;
-        let a = (Math::random() * f64::from(complexity * 5)).floor() as i32 + complexity;
-        let b = (Math::random() * f64::from(complexity * 2)).floor() as i32 + 1;
+        let a = (rng.random() * f64::from(complexity * 5)).floor() as i32 + complexity;
+        let b = (rng.random() * f64::from(complexity * 2)).floor() as i32 + 1;
 
-        let make_decimal = |n: i32| {
+        let mut make_decimal = |n: i32| {
             if use_decimals {
-                let d = denominators[(Math::random() * 4.0) as usize];
+                let d = denominators[(rng.random() * 4.0) as usize];
                 let val = f64::from(n * d) / f64::from(d);
                 (val, val.to_string())
             } else {
@@ -28,7 +28,7 @@ impl Expression {
 
         let (a_val, a_text) = make_decimal(a);
         let (b_val, b_text) = make_decimal(b);
-        let op = ops[(Math::random() * 4.0) as usize];
+        let op = ops[(rng.random() * 4.0) as usize];
 
         let (text, value) = match op {
             '+' => (format!("{a_text} + {b_text}"), a_val + b_val),
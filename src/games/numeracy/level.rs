@@ -1,4 +1,5 @@
 use super::expression::Expression;
+use crate::games::rng::Rng;
 #[derive(Debug)]
 pub struct Level {
     pub number: u32,
@@ -13,9 +14,9 @@ impl Level {
         }
     }
 
-    pub fn generate_expressions(&self) -> Vec<Expression> {
+    pub fn generate_expressions(&self, rng: &mut Rng) -> Vec<Expression> {
         (0..self.expressions_per_round)
-            .map(|_| Expression::new(self.number))
+            .map(|_| Expression::new(self.number, rng))
             .collect()
     }
 
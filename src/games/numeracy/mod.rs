@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::convert::TryFrom;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use web_sys::{Document, Element, Event, HtmlElement};
@@ -7,6 +8,12 @@ mod expression;
 mod level;
 mod state;
 
+use crate::games::{
+    clock,
+    recording::{Input, Recording},
+    settings,
+    share::Puzzle,
+};
 use expression::Expression;
 use level::Level;
 use state::GameState;
@@ -35,23 +42,6 @@ impl Numeracy {
             container: container.clone(),
         };
 
-        // Set up visibility change handler
-        let state = game.state.clone();
-        let doc = document.clone();
-        let visibility_callback = Closure::wrap(Box::new(move || {
-            let hidden = doc.hidden();
-            let mut game_state = state.borrow_mut();
-            game_state.is_visible = !hidden;
-            
-            if !hidden {
-                // When becoming visible, restart the current level
-                game_state.start_round();
-            }
-        }) as Box<dyn FnMut()>);
-
-        document.set_onvisibilitychange(Some(visibility_callback.as_ref().unchecked_ref()));
-        visibility_callback.forget();
-
         // Set up bubble click handlers once
         game.setup_bubble_handlers()?;
 
@@ -65,11 +55,21 @@ impl Numeracy {
             if let Some(bubble) = children.item(i) {
                 let bubble: Element = bubble.dyn_into::<Element>()?;
                 let state = self.state.clone();
+                let document = self.document.clone();
                 let bubble_ref = bubble.clone();
                 let i = i as usize;
                 let handler = Closure::wrap(Box::new(move |_event: Event| {
                     let mut state = state.borrow_mut();
-                    if state.toggle_selection(i) {
+                    if state.replaying || !clock::is_running() {
+                        return;
+                    }
+                    state.record(Input::Bubble(i));
+                    let changed = state.select_bubble(i);
+                    state.recording.publish(&document);
+                    if changed {
+                        if let Some(puzzle) = state.last_round.take() {
+                            puzzle.publish(&document);
+                        }
                         let is_selected = state.selected_indices.contains(&i);
                         let class = if is_selected {
                             "bubble selected"
@@ -77,12 +77,6 @@ impl Numeracy {
                             "bubble"
                         };
                         bubble_ref.set_attribute("class", class).unwrap();
-
-                        if state.selected_indices.len() == 3 {
-                            let round_success = state.check_current_round();
-                            state.update_score(round_success);
-                            state.start_round();
-                        }
                     }
                 }) as Box<dyn FnMut(_)>);
 
@@ -131,7 +125,9 @@ impl Numeracy {
         let state = self.state.borrow();
 
         if let Some(timer_elem) = self.document.get_element_by_id("timer") {
-            if state.is_visible {
+            if state.round_limit.is_none() {
+                timer_elem.set_text_content(Some("Untimed"));
+            } else if clock::is_running() {
                 if let Some(remaining) = state.get_round_time_remaining() {
                     let seconds = (remaining / 1000.0) as u32;
                     let text = format!("{}:{:02}", seconds / 60, seconds % 60);
@@ -172,10 +168,12 @@ impl Numeracy {
         {
             let mut state = self.state.borrow_mut();
             
-            // Only check time limits if the tab is visible
-            if state.is_visible {
+            // Only check time limits while the clock runs
+            if clock::is_running() {
                 if let Some(remaining) = state.get_round_time_remaining() {
                     if remaining <= 0.0 {
+                        state.record(Input::Timeout);
+                        state.recording.publish(&self.document);
                         state.update_score(false);
                         state.start_round();
                     }
@@ -190,10 +188,58 @@ impl Numeracy {
 
     #[wasm_bindgen]
     pub fn start(&self) -> Result<(), JsValue> {
+        clock::install("numeracy_paused")?;
+        settings::install('n')?;
         self.state.borrow_mut().start_level();
         self.render_bubbles()?;
         self.update_stats();
         self.start_timer()?;
         Ok(())
     }
+
+    /// Starts a session on a round someone shared, from its seed and level.
+    pub(crate) fn play_puzzle(puzzle: &Puzzle) -> Result<(), JsValue> {
+        let game = Numeracy::new()?;
+        let level = u32::try_from(puzzle.level).unwrap_or(1);
+        *game.state.borrow_mut() = GameState::with_seed(puzzle.seed, level);
+        puzzle.announce(&game.document);
+        game.start()
+    }
+
+    /// Re-drives a recorded session on a fresh board without the live timer.
+    pub(crate) fn replay(recording: &Recording) -> Result<(), JsValue> {
+        let game = Numeracy::new()?;
+        let level = u32::try_from(recording.level).unwrap_or(1);
+        let mut state = GameState::with_seed(recording.seed, level);
+        state.replaying = true;
+        *game.state.borrow_mut() = state;
+
+        game.state.borrow_mut().start_level();
+        game.render_bubbles()?;
+        game.update_stats();
+        game.update_timer();
+
+        recording.play(move |input| {
+            {
+                let mut state = game.state.borrow_mut();
+                match input {
+                    Input::Bubble(i) => {
+                        state.select_bubble(i);
+                    }
+                    Input::Timeout => {
+                        state.update_score(false);
+                        state.start_round();
+                    }
+                    Input::Level(level) => state.replay_level(level),
+                    Input::Reset => state.start_round(),
+                    _ => {}
+                }
+            }
+            game.update_timer();
+            if let Err(e) = game.render_bubbles() {
+                web_sys::console::log_2(&"Failed to replay input:".into(), &e);
+            }
+            game.update_stats();
+        })
+    }
 }
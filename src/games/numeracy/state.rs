@@ -1,5 +1,16 @@
 use super::{Expression, Level};
-use web_sys::{Performance, Storage};
+use crate::games::{
+    clock,
+    recording::{Input, Recording},
+    rng::{random_seed, Rng},
+    settings,
+    share::Puzzle,
+};
+use std::convert::TryFrom;
+use web_sys::Storage;
+
+/// Seconds per round unless the player's settings say otherwise.
+const ROUND_SECONDS: u32 = 15;
 
 #[derive(Debug)]
 pub struct GameState {
@@ -7,10 +18,19 @@ pub struct GameState {
     pub expressions: Vec<Expression>,
     pub selected_indices: Vec<usize>,
     pub round_start: Option<f64>,
+    /// Milliseconds allowed on the current round, or `None` when untimed.
+    pub round_limit: Option<f64>,
     pub level_start: Option<f64>,
     pub completed_rounds: u32,
-    pub is_visible: bool,
-    performance: Performance,
+    pub recording: Recording,
+    /// Set while re-driving a recorded session, which must not touch saved progress.
+    pub replaying: bool,
+    /// Seed that regenerates the current round's expressions.
+    pub round_seed: u32,
+    /// The last round solved, ready to be shared.
+    pub last_round: Option<Puzzle>,
+    round_taps: usize,
+    rng: Rng,
     storage: Storage,
 }
 
@@ -24,32 +44,46 @@ impl GameState {
             .and_then(|s| s.parse().ok())
             .unwrap_or(1);
 
+        Self::with_seed(random_seed(), level_number)
+    }
+
+    pub fn with_seed(seed: u32, level_number: u32) -> Self {
+        let window = web_sys::window().unwrap();
+        let storage = window.local_storage().unwrap().unwrap();
         let level = Level::new(level_number);
-        let expressions = level.generate_expressions();
-        let performance = window.performance().unwrap();
 
         Self {
             level,
-            expressions,
+            // Drawn by `start_level`, so a round's seed is the generator state
+            expressions: Vec::new(),
             selected_indices: Vec::new(),
             round_start: None,
+            round_limit: None,
             level_start: None,
             completed_rounds: 0,
-            is_visible: true,
-            performance,
+            recording: Recording::new('n', seed, level_number as usize),
+            replaying: false,
+            round_seed: seed,
+            last_round: None,
+            round_taps: 0,
+            rng: Rng::new(seed),
             storage,
         }
     }
 
     pub fn start_level(&mut self) {
-        self.level_start = Some(self.performance.now());
+        self.level_start = Some(clock::now());
         self.start_round();
     }
 
     pub fn start_round(&mut self) {
-        self.expressions = self.level.generate_expressions();
+        self.round_seed = self.rng.state();
+        self.round_taps = 0;
+        self.expressions = self.level.generate_expressions(&mut self.rng);
         self.selected_indices.clear();
-        self.round_start = Some(self.performance.now());
+        self.round_start = Some(clock::now());
+        self.round_limit = settings::time_limit('n', ROUND_SECONDS, self.level.number as usize)
+            .map(|seconds| f64::from(seconds) * 1000.0);
     }
 
     pub fn toggle_selection(&mut self, index: usize) -> bool {
@@ -64,6 +98,46 @@ impl GameState {
         }
     }
 
+    pub fn record(&mut self, input: Input) {
+        if !self.replaying {
+            self.recording.record(input);
+        }
+    }
+
+    /// Applies a bubble tap, finishing the round once three are selected.
+    /// Returns whether the tap changed the selection.
+    pub fn select_bubble(&mut self, index: usize) -> bool {
+        if !self.toggle_selection(index) {
+            return false;
+        }
+        self.round_taps += 1;
+        // A replay ends the round on the `Level` input logged after this tap
+        if self.selected_indices.len() == 3 && !self.replaying {
+            let round_success = self.check_current_round();
+            if round_success {
+                let seconds = self
+                    .round_start
+                    .map_or(0, |start| ((clock::now() - start) / 1000.0) as u32);
+                self.last_round = Some(
+                    Puzzle::new('n', self.round_seed, self.level.number as usize)
+                        .solved(self.round_taps, seconds),
+                );
+            }
+            self.update_score(round_success);
+            self.record(Input::Level(self.level.number as usize));
+            self.start_round();
+        }
+        true
+    }
+
+    /// Ends a replayed round as the log says it ended, with the next round
+    /// played at `level`.
+    pub fn replay_level(&mut self, level: usize) {
+        self.level = Level::new(u32::try_from(level).unwrap_or(1));
+        self.completed_rounds += 1;
+        self.start_round();
+    }
+
     pub fn check_current_round(&self) -> bool {
         if self.selected_indices.len() != 3 {
             return false;
@@ -78,13 +152,16 @@ impl GameState {
         Level::check_order(&selected_expressions)
     }
 
+    /// Milliseconds left on the round, or `None` if it is untimed or not
+    /// yet started.
     pub fn get_round_time_remaining(&self) -> Option<f64> {
+        let limit = self.round_limit?;
         self.round_start.map(|start| {
-            let elapsed = self.performance.now() - start;
-            if elapsed >= 15000.0 {
+            let elapsed = clock::now() - start;
+            if elapsed >= limit {
                 0.0
             } else {
-                15000.0 - elapsed
+                limit - elapsed
             }
         })
     }
@@ -96,7 +173,10 @@ impl GameState {
 
         if round_success {
             // Calculate level jumps based on time bonus using a mathematical formula
-            let level_jump = if time_bonus > 0 {
+            // Untimed rounds earn no bonus, so each success climbs one level
+            let level_jump = if self.round_limit.is_none() {
+                1
+            } else if time_bonus > 0 {
                 ((f64::from(time_bonus) * 0.2).floor() as u32).min(3)
             } else {
                 0
@@ -105,18 +185,22 @@ impl GameState {
             if level_jump > 0 {
                 let new_level = self.level.number + level_jump;
                 self.level = Level::new(new_level);
-                self.storage
-                    .set_item("numeracy_level", &new_level.to_string())
-                    .unwrap();
+                self.save_level();
             }
         } else if self.level.number > 1 {
             let new_level = self.level.number - 1;
             self.level = Level::new(new_level);
-            self.storage
-                .set_item("numeracy_level", &new_level.to_string())
-                .unwrap();
+            self.save_level();
         }
 
         self.completed_rounds += 1;
     }
+
+    fn save_level(&self) {
+        if !self.replaying {
+            self.storage
+                .set_item("numeracy_level", &self.level.number.to_string())
+                .unwrap();
+        }
+    }
 }
@@ -0,0 +1,115 @@
+use super::{levels::Stage, Perception};
+use crate::games::rng::Rng;
+use std::collections::{HashSet, VecDeque};
+use web_sys::Document;
+
+/// Mazes generated per level, of which the one closest to the level's target
+/// difficulty is played.
+const CANDIDATES: usize = 6;
+
+/// What makes one maze harder than another of the same size.
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct Metrics {
+    /// Clicks on the shortest solution.
+    pub(super) solution_length: usize,
+    /// Changes of direction along the solution.
+    pub(super) turns: usize,
+    /// Cells with a single way out.
+    pub(super) dead_ends: usize,
+    /// Cells on the solution offering more than one way on.
+    pub(super) decision_points: usize,
+    /// Extra steps the key adds over walking straight to the door.
+    pub(super) key_detour: usize,
+}
+
+impl Metrics {
+    pub(super) fn score(&self) -> f64 {
+        (self.solution_length + self.turns + 2 * self.decision_points + self.key_detour) as f64
+            + self.dead_ends as f64 / 2.0
+    }
+}
+
+/// Score a maze of this size should land near; grows with the cell count so
+/// each level is a step up from the last.
+fn target_score(size: usize, floors: usize) -> f64 {
+    0.8 * (size * size * floors) as f64
+}
+
+impl Perception {
+    /// Measures the maze from its start, or `None` if it cannot be solved.
+    pub(super) fn metrics(&self) -> Option<Metrics> {
+        let clicks = self.solve_from_start()?;
+        let mut metrics = Metrics {
+            solution_length: clicks.len(),
+            ..Metrics::default()
+        };
+
+        let mut pos = self.start_position;
+        let mut heading = None;
+        for &click in &clicks {
+            let step = (
+                click.0 as isize - pos.0 as isize,
+                click.1 as isize - pos.1 as isize,
+            );
+            if heading.is_some_and(|h| h != step) {
+                metrics.turns += 1;
+            }
+            if self.open_neighbours(pos).len() > 2 {
+                metrics.decision_points += 1;
+            }
+            heading = Some(step);
+            pos = self.landing(click);
+        }
+
+        metrics.dead_ends = (0..self.size * self.rows())
+            .filter(|&cell| {
+                self.open_neighbours((cell % self.size, cell / self.size))
+                    .len()
+                    == 1
+            })
+            .count();
+
+        let via_key = self.distance(self.start_position, self.key_position)?
+            + self.distance(self.key_position, self.door_position)?;
+        let direct = self.distance(self.start_position, self.door_position)?;
+        metrics.key_detour = via_key.saturating_sub(direct);
+        Some(metrics)
+    }
+
+    /// Steps between two cells, ignoring keys and doors.
+    fn distance(&self, from: (usize, usize), to: (usize, usize)) -> Option<usize> {
+        let mut queue = VecDeque::from([(from, 0)]);
+        let mut seen = HashSet::from([from]);
+        while let Some((pos, steps)) = queue.pop_front() {
+            if pos == to {
+                return Some(steps);
+            }
+            for next in self.open_neighbours(pos) {
+                let next = self.landing(next);
+                if seen.insert(next) {
+                    queue.push_back((next, steps + 1));
+                }
+            }
+        }
+        None
+    }
+
+    /// Generates several mazes from `seed` and keeps the one whose difficulty
+    /// is closest to the target for its size. The chosen maze keeps its own
+    /// seed, so `generate` alone regenerates it for replays and sharing.
+    pub(super) fn graded_maze(stage: &Stage, floors: usize, seed: u32, document: &Document) -> Self {
+        let mut rng = Rng::new(seed);
+        let target = target_score(stage.size, floors);
+        (0..CANDIDATES)
+            .map(|_| Self::generate(stage, floors, rng.next_u32(), document.clone()))
+            .filter_map(|maze| {
+                let score = maze.metrics()?.score();
+                Some(((score - target).abs(), maze))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map_or_else(
+                || Self::generate(stage, floors, seed, document.clone()),
+                |(_, maze)| maze,
+            )
+    }
+}
@@ -0,0 +1,369 @@
+//! Plain-text and SVG drawings of a maze, and a parser for the text form.
+//!
+//! The text form draws each cell three characters wide, with the boundary
+//! to its right after it:
+//!
+//! ```text
+//! +---+---+
+//! | S   K |
+//! +   +-v-+
+//! |     D |
+//! +---+---+
+//! ```
+//!
+//! `S`, `K` and `D` mark the start, key and door. A boundary is a wall
+//! (`|`, `---`), open (spaces) or one-way, drawn as the direction it can be
+//! crossed in (`>`, `<`, `-v-`, `-^-`).
+
+use super::{get_document, hazards::Hazards, levels::Stage, objectives::Objectives, Perception};
+use std::{collections::HashSet, fmt::Write};
+use wasm_bindgen::prelude::*;
+
+/// Pixel size of one cell in the SVG drawing.
+const SVG_CELL: usize = 40;
+
+/// What lies between a cell and its right or bottom neighbour.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Boundary {
+    Open,
+    Wall,
+    /// Only passable from the first cell to the second.
+    Forward,
+    /// Only passable from the second cell to the first.
+    Backward,
+}
+
+/// The parts of a single-floor maze the text and SVG drawings show.
+struct Plan {
+    size: usize,
+    /// Four per cell, as in `Perception::walls`.
+    walls: Vec<bool>,
+    start: (usize, usize),
+    key: (usize, usize),
+    door: (usize, usize),
+}
+
+impl Plan {
+    /// What lies between `a` and its right or bottom neighbour `b`.
+    fn boundary(&self, a: (usize, usize), b: (usize, usize)) -> Boundary {
+        let cell = |(x, y): (usize, usize)| (y * self.size + x) * 4;
+        // The wall on a's right or bottom side, and on b's left or top side
+        let (out_side, in_side) = if b.0 > a.0 { (1, 3) } else { (2, 0) };
+        let a_to_b = self.walls[cell(a) + out_side];
+        let b_to_a = self.walls[cell(b) + in_side];
+        match (a_to_b, b_to_a) {
+            (false, false) => Boundary::Open,
+            (true, true) => Boundary::Wall,
+            (false, true) => Boundary::Forward,
+            (true, false) => Boundary::Backward,
+        }
+    }
+
+    fn marker(&self, pos: (usize, usize)) -> char {
+        if pos == self.start {
+            'S'
+        } else if pos == self.key {
+            'K'
+        } else if pos == self.door {
+            'D'
+        } else {
+            ' '
+        }
+    }
+
+    fn to_ascii(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&"+---".repeat(self.size));
+        out.push_str("+\n");
+        for y in 0..self.size {
+            out.push('|');
+            for x in 0..self.size {
+                let _ = write!(out, " {} ", self.marker((x, y)));
+                out.push(if x + 1 == self.size {
+                    '|'
+                } else {
+                    match self.boundary((x, y), (x + 1, y)) {
+                        Boundary::Open => ' ',
+                        Boundary::Wall => '|',
+                        Boundary::Forward => '>',
+                        Boundary::Backward => '<',
+                    }
+                });
+            }
+            out.push('\n');
+            for x in 0..self.size {
+                out.push('+');
+                out.push_str(if y + 1 == self.size {
+                    "---"
+                } else {
+                    match self.boundary((x, y), (x, y + 1)) {
+                        Boundary::Open => "   ",
+                        Boundary::Wall => "---",
+                        Boundary::Forward => "-v-",
+                        Boundary::Backward => "-^-",
+                    }
+                });
+            }
+            out.push_str("+\n");
+        }
+        out
+    }
+
+    fn to_svg(&self) -> String {
+        let side = self.size * SVG_CELL;
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-2 -2 {0} {0}\" \
+             width=\"{0}\" height=\"{0}\" stroke=\"black\" stroke-width=\"3\" \
+             stroke-linecap=\"square\">",
+            side + 4
+        );
+        let _ = write!(
+            out,
+            "<rect width=\"{side}\" height=\"{side}\" fill=\"white\" />"
+        );
+
+        let mut line = |(x1, y1): (usize, usize), (x2, y2): (usize, usize), kind: Boundary| {
+            let dash = match kind {
+                Boundary::Open => return,
+                Boundary::Wall => "",
+                Boundary::Forward | Boundary::Backward => " stroke-dasharray=\"4 4\"",
+            };
+            let _ = write!(
+                out,
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"{dash} />",
+                x1 * SVG_CELL,
+                y1 * SVG_CELL,
+                x2 * SVG_CELL,
+                y2 * SVG_CELL
+            );
+        };
+        line((0, 0), (self.size, 0), Boundary::Wall);
+        line((0, 0), (0, self.size), Boundary::Wall);
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let right = if x + 1 == self.size {
+                    Boundary::Wall
+                } else {
+                    self.boundary((x, y), (x + 1, y))
+                };
+                let bottom = if y + 1 == self.size {
+                    Boundary::Wall
+                } else {
+                    self.boundary((x, y), (x, y + 1))
+                };
+                line((x + 1, y), (x + 1, y + 1), right);
+                line((x, y + 1), (x + 1, y + 1), bottom);
+            }
+        }
+
+        for (pos, label) in [(self.start, "👤"), (self.key, "🔑"), (self.door, "🚪")] {
+            let _ = write!(
+                out,
+                "<text x=\"{}\" y=\"{}\" font-size=\"{}\" stroke=\"none\" \
+                 text-anchor=\"middle\" dominant-baseline=\"central\">{label}</text>",
+                pos.0 * SVG_CELL + SVG_CELL / 2,
+                pos.1 * SVG_CELL + SVG_CELL / 2,
+                SVG_CELL * 3 / 5
+            );
+        }
+        out.push_str("</svg>");
+        out
+    }
+
+    /// Reads the text form: walls, start, key and door.
+    fn parse(text: &str) -> Result<Self, String> {
+        let lines: Vec<Vec<char>> = text
+            .lines()
+            .map(|line| line.trim_end().chars().collect())
+            .filter(|line: &Vec<char>| !line.is_empty())
+            .collect();
+        if lines.len() < 3 || lines.len().is_multiple_of(2) {
+            return Err(format!(
+                "expected an odd number of lines, got {}",
+                lines.len()
+            ));
+        }
+        let size = (lines.len() - 1) / 2;
+        if lines[0].len() != size * 4 + 1 {
+            return Err(format!(
+                "expected a square maze {} characters wide, got {}",
+                size * 4 + 1,
+                lines[0].len()
+            ));
+        }
+        // Trailing spaces may have been trimmed by an editor
+        let at = |row: usize, col: usize| lines[row].get(col).copied().unwrap_or(' ');
+
+        let mut walls = vec![true; size * size * 4];
+        let (mut start, mut key, mut door) = (None, None, None);
+        for y in 0..size {
+            for x in 0..size {
+                let cell = (y * size + x) * 4;
+                let marker = match at(2 * y + 1, 4 * x + 2) {
+                    ' ' => None,
+                    'S' => start.replace((x, y)),
+                    'K' => key.replace((x, y)),
+                    'D' => door.replace((x, y)),
+                    other => return Err(format!("unknown marker '{other}' at ({x}, {y})")),
+                };
+                if marker.is_some() {
+                    return Err(format!("duplicate marker at ({x}, {y})"));
+                }
+
+                if x + 1 < size {
+                    let right = cell + 1;
+                    let left_of_next = cell + 4 + 3;
+                    match at(2 * y + 1, 4 * x + 4) {
+                        ' ' => (walls[right], walls[left_of_next]) = (false, false),
+                        '|' => {}
+                        '>' => walls[right] = false,
+                        '<' => walls[left_of_next] = false,
+                        other => return Err(format!("unknown wall '{other}' right of ({x}, {y})")),
+                    }
+                }
+                if y + 1 < size {
+                    let bottom = cell + 2;
+                    let top_of_next = cell + size * 4;
+                    match at(2 * y + 2, 4 * x + 2) {
+                        ' ' => (walls[bottom], walls[top_of_next]) = (false, false),
+                        '-' => {}
+                        'v' => walls[bottom] = false,
+                        '^' => walls[top_of_next] = false,
+                        other => return Err(format!("unknown wall '{other}' below ({x}, {y})")),
+                    }
+                }
+            }
+        }
+        let (Some(start), Some(key), Some(door)) = (start, key, door) else {
+            return Err("the maze needs an S, a K and a D".to_string());
+        };
+        Ok(Self {
+            size,
+            walls,
+            start,
+            key,
+            door,
+        })
+    }
+}
+
+impl Perception {
+    fn plan(&self) -> Plan {
+        Plan {
+            size: self.size,
+            walls: self.walls.clone(),
+            start: self.start_position,
+            key: self.key_position,
+            door: self.door_position,
+        }
+    }
+
+    /// Builds a playable maze from its text form.
+    ///
+    /// Only walls, start, key and door are read; the maze has no objectives,
+    /// hazards or teleporters.
+    pub(crate) fn from_ascii(text: &str) -> Result<Self, String> {
+        let plan = Plan::parse(text)?;
+        let mut game = Self::create_maze(&Stage::sized(plan.size), 0, get_document());
+        game.level = plan.size.saturating_sub(1).max(1);
+        game.walls = plan.walls;
+        game.start_position = plan.start;
+        game.current_position = plan.start;
+        game.key_position = plan.key;
+        game.door_position = plan.door;
+        game.visited = HashSet::from([plan.start]);
+        game.path = vec![plan.start];
+        game.objectives = Objectives::default();
+        game.hazards = Hazards::default();
+        game.teleporters.clear();
+        if game.solve_from_start().is_none() {
+            return Err("the door cannot be reached with the key".to_string());
+        }
+        Ok(game)
+    }
+
+    /// Starts a session on a hand-authored maze. Like a replay, it is kept
+    /// out of the saved progress and the session log.
+    pub(crate) fn play_ascii(text: &str) -> Result<(), JsValue> {
+        let mut game = Self::from_ascii(text).map_err(|e| JsValue::from_str(&e))?;
+        game.replaying = true;
+        game.render()?;
+        game.start()
+    }
+
+    /// Points the export links at the current maze.
+    pub(super) fn publish_exports(&self) -> Result<(), JsValue> {
+        // The text and SVG formats only describe a single floor
+        if let Some(export) = self.document.get_element_by_id("export") {
+            if self.floors > 1 {
+                export.set_attribute("hidden", "")?;
+                return Ok(());
+            }
+            export.remove_attribute("hidden")?;
+        }
+        let plan = self.plan();
+        for (id, mime, body) in [
+            ("export-text", "text/plain", plan.to_ascii()),
+            ("export-svg", "image/svg+xml", plan.to_svg()),
+        ] {
+            if let Some(link) = self.document.get_element_by_id(id) {
+                let data = js_sys::encode_uri_component(&body);
+                link.set_attribute("href", &format!("data:{mime};charset=utf-8,{data}"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAZE: &str = "\
++---+---+---+
+| S   K <   |
++   +-v-+   +
+|   |   >   |
++-^-+---+   +
+|         D |
++---+---+---+
+";
+
+    #[test]
+    fn text_form_round_trips() {
+        let plan = Plan::parse(MAZE).unwrap();
+        assert_eq!(plan.size, 3);
+        assert_eq!((plan.start, plan.key, plan.door), ((0, 0), (1, 0), (2, 2)));
+        assert_eq!(plan.to_ascii(), MAZE);
+    }
+
+    #[test]
+    fn one_way_boundaries_only_open_one_way() {
+        let plan = Plan::parse(MAZE).unwrap();
+        assert_eq!(plan.boundary((0, 0), (1, 0)), Boundary::Open);
+        assert_eq!(plan.boundary((1, 0), (2, 0)), Boundary::Backward);
+        assert_eq!(plan.boundary((1, 0), (1, 1)), Boundary::Forward);
+        assert_eq!(plan.boundary((0, 1), (0, 2)), Boundary::Backward);
+        assert_eq!(plan.boundary((1, 1), (2, 1)), Boundary::Forward);
+        assert_eq!(plan.boundary((0, 1), (1, 1)), Boundary::Wall);
+    }
+
+    #[test]
+    fn trimmed_trailing_spaces_still_parse() {
+        let trimmed: Vec<&str> = MAZE.lines().map(str::trim_end).collect();
+        assert_eq!(Plan::parse(&trimmed.join("\n")).unwrap().to_ascii(), MAZE);
+    }
+
+    #[test]
+    fn malformed_mazes_are_rejected() {
+        let error = |text: &str| Plan::parse(text).err().unwrap();
+        assert!(error("+---+\n| S |\n").contains("odd number of lines"));
+        assert!(error("+---+\n| S |\n|   |\n+---+\n").contains("odd number of lines"));
+        assert!(error("+----+\n| S  |\n+----+\n").contains("characters wide"));
+        assert!(error(&MAZE.replace('K', "X")).contains("unknown marker 'X'"));
+        assert!(error(&MAZE.replace('K', "S")).contains("duplicate marker"));
+        assert!(error(&MAZE.replace('D', " ")).contains("needs an S, a K and a D"));
+        assert!(error(&MAZE.replace('>', "#")).contains("unknown wall '#'"));
+        assert!(error(&MAZE.replace("-v-", "-x-")).contains("unknown wall 'x'"));
+    }
+}
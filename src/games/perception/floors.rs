@@ -0,0 +1,290 @@
+//! Multi-floor mazes.
+//!
+//! Floors are stacked as extra rows: cell `(x, y)` lies on floor `y / size`,
+//! so walls, positions and the solver work unchanged across floors. Rows at
+//! a floor's edge are always walled off from the next floor; the only way
+//! between floors is a stair, which links a cell to the one directly above.
+
+use super::{hazards::Hazards, levels::Stage, objectives::Objectives, Perception};
+use crate::games::{clock, recording::Input, rng::Rng};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+use wasm_bindgen::prelude::*;
+use web_sys::{Document, HtmlSelectElement};
+
+/// Most floors a maze can be built with.
+pub(super) const MAX_FLOORS: usize = 3;
+/// Chance that the generator climbs to another floor when it could stay.
+const STAIR_CHANCE: f64 = 0.15;
+
+pub(super) fn default_floors() -> usize {
+    1
+}
+
+impl Perception {
+    /// Builds a maze of `floors` stacked floors of the stage's size, or a
+    /// flat maze for a single floor. Stacked floors are always carved by
+    /// depth-first search, whatever the stage's generator.
+    pub(super) fn generate(stage: &Stage, floors: usize, seed: u32, document: Document) -> Self {
+        if floors <= 1 {
+            return Self::create_maze(stage, seed, document);
+        }
+
+        // Start from a flat maze for every other field, then replace its layout
+        let mut game = Self::create_maze(stage, seed, document);
+        let size = stage.size;
+        let mut rng = Rng::new(seed);
+        let rows = size * floors;
+        let cells = size * rows;
+        let mut walls = vec![true; cells * 4];
+        let mut stairs = Vec::new();
+
+        // Neighbours on the same floor, as (cell, wall in cell, wall in neighbour)
+        let level_neighbours = |cell: usize| {
+            let (x, y) = (cell % size, cell / size);
+            let mut out = Vec::new();
+            if y % size > 0 {
+                out.push((cell - size, 0, 2));
+            }
+            if x + 1 < size {
+                out.push((cell + 1, 1, 3));
+            }
+            if y % size + 1 < size {
+                out.push((cell + size, 2, 0));
+            }
+            if x > 0 {
+                out.push((cell - 1, 3, 1));
+            }
+            out
+        };
+        let floor_size = size * size;
+
+        // Iterative DFS over the 3D lattice, climbing only now and then
+        let mut visited = vec![false; cells];
+        let first = rng.below(cells);
+        visited[first] = true;
+        let mut stack = vec![first];
+        while let Some(&cell) = stack.last() {
+            let level: Vec<_> = level_neighbours(cell)
+                .into_iter()
+                .filter(|&(n, _, _)| !visited[n])
+                .collect();
+            let vertical: Vec<usize> = [cell.checked_sub(floor_size), Some(cell + floor_size)]
+                .iter()
+                .flatten()
+                .copied()
+                .filter(|&n| n < cells && !visited[n])
+                .collect();
+
+            if !vertical.is_empty() && (level.is_empty() || rng.random() < STAIR_CHANCE) {
+                let next = vertical[rng.below(vertical.len())];
+                let lower = cell.min(next);
+                stairs.push((lower % size, lower / size));
+                visited[next] = true;
+                stack.push(next);
+            } else if let Some(&(next, wall, back)) = level.get(rng.below(level.len())) {
+                walls[cell * 4 + wall] = false;
+                walls[next * 4 + back] = false;
+                visited[next] = true;
+                stack.push(next);
+            } else {
+                stack.pop();
+            }
+        }
+
+        game.floors = floors;
+        game.recording = game.recording.variant(floors);
+        game.walls = walls;
+        game.stairs = stairs;
+        game.objectives = Objectives::default();
+        game.hazards = Hazards::default();
+        game.teleporters.clear();
+
+        // Start and door at the two ends of the longest route, key halfway
+        let far = *game
+            .route_to_farthest((first % size, first / size))
+            .last()
+            .unwrap_or(&(0, 0));
+        let route = game.route_to_farthest(far);
+        let door = route[route.len() - 1];
+        game.start_position = far;
+        game.current_position = far;
+        game.door_position = door;
+        game.key_position = if route.len() >= 3 {
+            route[1 + rng.below(route.len() - 2)]
+        } else {
+            far
+        };
+        game.visited.clear();
+        game.visited.insert(far);
+        game.path = vec![far];
+        game
+    }
+
+    /// Shortest route from `from` to the cell farthest from it, both ends
+    /// included.
+    fn route_to_farthest(&self, from: (usize, usize)) -> Vec<(usize, usize)> {
+        let mut parent = vec![None; self.size * self.rows()];
+        let mut seen = vec![false; self.size * self.rows()];
+        let mut queue = VecDeque::from([from]);
+        seen[from.1 * self.size + from.0] = true;
+        let mut last = from;
+        while let Some(pos) = queue.pop_front() {
+            last = pos;
+            for next in self.open_neighbours(pos) {
+                let index = next.1 * self.size + next.0;
+                if !seen[index] {
+                    seen[index] = true;
+                    parent[index] = Some(pos);
+                    queue.push_back(next);
+                }
+            }
+        }
+        let mut route = vec![last];
+        while let Some(previous) = parent[last.1 * self.size + last.0] {
+            route.push(previous);
+            last = previous;
+        }
+        route.reverse();
+        route
+    }
+
+    /// Rows across every floor.
+    pub(super) fn rows(&self) -> usize {
+        self.size * self.floors
+    }
+
+    /// Floor the player is on, counted from 0.
+    pub(super) fn current_floor(&self) -> usize {
+        self.current_position.1 / self.size
+    }
+
+    /// Index of `(x, y)` among the grid's cells, if it is on the floor shown.
+    pub(super) fn dom_index(&self, (x, y): (usize, usize)) -> Option<u32> {
+        (y / self.size == self.current_floor()).then(|| ((y % self.size) * self.size + x) as u32)
+    }
+
+    /// Cell reached by taking the stairs at `pos` up or down, if there are any.
+    pub(super) fn stair_target(&self, (x, y): (usize, usize), up: bool) -> Option<(usize, usize)> {
+        if up {
+            self.stairs.contains(&(x, y)).then_some((x, y + self.size))
+        } else {
+            y.checked_sub(self.size)
+                .filter(|&below| self.stairs.contains(&(x, below)))
+                .map(|below| (x, below))
+        }
+    }
+
+    /// Climbs the stairs at the current cell: 0 if there are none that way,
+    /// otherwise as for `try_move`.
+    pub(super) fn try_climb(&mut self, up: bool) -> i32 {
+        let Some((x, y)) = self.stair_target(self.current_position, up) else {
+            return 0;
+        };
+        if self.is_locked((x, y)) {
+            return 0;
+        }
+        self.hazards.leave(self.current_position);
+        self.step_to(x, y)
+    }
+
+    /// Switches to mazes of `floors` floors, starting a fresh maze now.
+    pub(super) fn set_floors(&mut self, floors: usize) {
+        self.floors = floors.clamp(1, MAX_FLOORS);
+        self.reset();
+    }
+
+    pub(super) fn render_floor(&self) -> Result<(), JsValue> {
+        if let Some(indicator) = self.document.get_element_by_id("floor") {
+            indicator.set_text_content(Some(&format!(
+                "{}/{}",
+                self.current_floor() + 1,
+                self.floors
+            )));
+        }
+        if let Some(controls) = self.document.get_element_by_id("floor-controls") {
+            if self.floors > 1 {
+                controls.remove_attribute("hidden")?;
+            } else {
+                controls.set_attribute("hidden", "")?;
+            }
+        }
+        let hint_floor = self.hint.map(|(_, y)| y / self.size);
+        for (id, up) in [("floor-up", true), ("floor-down", false)] {
+            if let Some(button) = self.document.get_element_by_id(id) {
+                if self.stair_target(self.current_position, up).is_some() {
+                    button.remove_attribute("disabled")?;
+                } else {
+                    button.set_attribute("disabled", "")?;
+                }
+                let hinted = hint_floor.is_some_and(|floor| {
+                    let current = self.current_floor();
+                    if up {
+                        floor > current
+                    } else {
+                        floor < current
+                    }
+                });
+                button.class_list().toggle_with_force("hint", hinted)?;
+            }
+        }
+        if let Some(select) = self.document.get_element_by_id("floors") {
+            if let Ok(select) = select.dyn_into::<HtmlSelectElement>() {
+                select.set_value(&self.floors.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn setup_floor_handlers(game_state: &Rc<RefCell<Self>>) -> Result<(), JsValue> {
+        let document = game_state.borrow().document.clone();
+
+        for (id, input) in [("floor-up", Input::Up), ("floor-down", Input::Down)] {
+            let Some(button) = document.get_element_by_id(id) else {
+                continue;
+            };
+            let game_state = game_state.clone();
+            let handler = Closure::wrap(Box::new(move |_event: web_sys::MouseEvent| {
+                if !clock::is_running() {
+                    return;
+                }
+                if let Ok(mut game) = game_state.try_borrow_mut() {
+                    game.record_input(input);
+                    game.apply_input(input).unwrap_or_else(|e| {
+                        web_sys::console::log_2(&"Failed to climb:".into(), &e);
+                    });
+                }
+            }) as Box<dyn FnMut(_)>);
+            button.add_event_listener_with_callback("click", handler.as_ref().unchecked_ref())?;
+            handler.forget();
+        }
+
+        if let Some(select) = document.get_element_by_id("floors") {
+            let select: HtmlSelectElement = select.dyn_into()?;
+            let handler = {
+                let game_state = game_state.clone();
+                let select = select.clone();
+                Closure::wrap(Box::new(move |_event: web_sys::Event| {
+                    if let Ok(mut game) = game_state.try_borrow_mut() {
+                        // A new maze would start under the pause overlay, so
+                        // put the choice back until the game resumes
+                        if !clock::is_running() {
+                            select.set_value(&game.floors.to_string());
+                            return;
+                        }
+                        let floors = select.value().parse().unwrap_or(1);
+                        if floors == game.floors {
+                            return;
+                        }
+                        game.record_input(Input::Floors(floors));
+                        game.apply_input(Input::Floors(floors)).unwrap_or_else(|e| {
+                            web_sys::console::log_2(&"Failed to change floors:".into(), &e);
+                        });
+                    }
+                }) as Box<dyn FnMut(_)>)
+            };
+            select.add_event_listener_with_callback("change", handler.as_ref().unchecked_ref())?;
+            handler.forget();
+        }
+        Ok(())
+    }
+}
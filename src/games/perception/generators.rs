@@ -0,0 +1,146 @@
+use crate::games::rng::Rng;
+use serde::{Deserialize, Serialize};
+
+// Directions: (dr, dc, current wall index, neighbor wall index)
+// Up: (r-1, c) uses wall 0 in current and 2 in neighbor.
+// Right: (r, c+1) uses wall 1 in current and 3 in neighbor.
+// Down: (r+1, c) uses wall 2 in current and 0 in neighbor.
+// Left: (r, c-1) uses wall 3 in current and 1 in neighbor.
+pub(super) const DIRECTIONS: [(isize, isize, usize, usize); 4] =
+    [(-1, 0, 0, 2), (0, 1, 1, 3), (1, 0, 2, 0), (0, -1, 3, 1)];
+
+/// Algorithm that carves the spanning tree of a new maze.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum Generator {
+    /// Depth-first search: long winding corridors with few branches.
+    #[default]
+    Backtracker,
+    /// Randomised Prim's: short corridors and many small dead ends.
+    Prim,
+}
+
+/// In-bounds neighbours of `(r, c)` as `(row, col, wall in cell, wall in neighbour)`.
+fn neighbours(size: usize, (r, c): (usize, usize)) -> Vec<(usize, usize, usize, usize)> {
+    DIRECTIONS
+        .iter()
+        .filter_map(|&(dr, dc, cur_wall, nb_wall)| {
+            let nr = r as isize + dr;
+            let nc = c as isize + dc;
+            (nr >= 0 && nr < size as isize && nc >= 0 && nc < size as isize)
+                .then_some((nr as usize, nc as usize, cur_wall, nb_wall))
+        })
+        .collect()
+}
+
+impl Generator {
+    /// Walls (4 per cell, as in `Perception::walls`) of a perfect maze grown
+    /// from `start`, given as (row, col).
+    pub(super) fn carve(self, size: usize, start: (usize, usize), rng: &mut Rng) -> Vec<bool> {
+        match self {
+            Generator::Backtracker => backtracker(size, start, rng),
+            Generator::Prim => prim(size, start, rng),
+        }
+    }
+}
+
+fn backtracker(size: usize, (start_row, start_col): (usize, usize), rng: &mut Rng) -> Vec<bool> {
+    let idx = |r: usize, c: usize| r * size + c; // utility: (row, col) -> index
+    let mut walls = vec![true; size * size * 4];
+    let mut visited_cells = vec![false; size * size];
+
+    let mut stack = vec![(start_row, start_col)];
+    visited_cells[idx(start_row, start_col)] = true;
+
+    // Iterative DFS: remove walls to create a spanning tree
+    while let Some((r, c)) = stack.last().copied() {
+        let mut neighbors: Vec<_> = neighbours(size, (r, c))
+            .into_iter()
+            .filter(|&(nr, nc, _, _)| !visited_cells[idx(nr, nc)])
+            .collect();
+
+        if neighbors.is_empty() {
+            stack.pop();
+        } else {
+            // Shuffle neighbors with the maze's generator
+            neighbors.sort_by(|_, _| {
+                if rng.random() < 0.5 {
+                    std::cmp::Ordering::Less
+                } else {
+                    std::cmp::Ordering::Greater
+                }
+            });
+            let (nr, nc, cur_wall, nb_wall) = neighbors[0];
+            // Remove walls between current and neighbor
+            walls[idx(r, c) * 4 + cur_wall] = false;
+            walls[idx(nr, nc) * 4 + nb_wall] = false;
+            visited_cells[idx(nr, nc)] = true;
+            stack.push((nr, nc));
+        }
+    }
+    walls
+}
+
+fn prim(size: usize, start: (usize, usize), rng: &mut Rng) -> Vec<bool> {
+    let idx = |r: usize, c: usize| r * size + c;
+    let mut walls = vec![true; size * size * 4];
+    let mut in_maze = vec![false; size * size];
+    in_maze[idx(start.0, start.1)] = true;
+
+    // Walls between the maze so far and the cells around it
+    let mut frontier: Vec<_> = neighbours(size, start)
+        .into_iter()
+        .map(|edge| (start, edge))
+        .collect();
+    while !frontier.is_empty() {
+        let ((r, c), (nr, nc, cur_wall, nb_wall)) = frontier.swap_remove(rng.below(frontier.len()));
+        if in_maze[idx(nr, nc)] {
+            continue;
+        }
+        walls[idx(r, c) * 4 + cur_wall] = false;
+        walls[idx(nr, nc) * 4 + nb_wall] = false;
+        in_maze[idx(nr, nc)] = true;
+        frontier.extend(
+            neighbours(size, (nr, nc))
+                .into_iter()
+                .filter(|&(fr, fc, _, _)| !in_maze[idx(fr, fc)])
+                .map(|edge| ((nr, nc), edge)),
+        );
+    }
+    walls
+}
+
+/// Opens roughly `share` of the dead ends into loops, preferring to join two
+/// dead ends at once.
+pub(super) fn braid(walls: &mut [bool], size: usize, share: f64, rng: &mut Rng) {
+    if share <= 0.0 {
+        return;
+    }
+    let open_sides =
+        |walls: &[bool], cell: usize| (0..4).filter(|&side| !walls[cell * 4 + side]).count();
+
+    for cell in 0..size * size {
+        // An earlier opening may already have joined this one up
+        if open_sides(walls, cell) != 1 || rng.random() >= share {
+            continue;
+        }
+        let closed: Vec<_> = neighbours(size, (cell / size, cell % size))
+            .into_iter()
+            .filter(|&(_, _, cur_wall, _)| walls[cell * 4 + cur_wall])
+            .collect();
+        let dead_ends: Vec<_> = closed
+            .iter()
+            .copied()
+            .filter(|&(nr, nc, _, _)| open_sides(walls, nr * size + nc) == 1)
+            .collect();
+        let choices = if dead_ends.is_empty() {
+            closed
+        } else {
+            dead_ends
+        };
+        if let Some(&(nr, nc, cur_wall, nb_wall)) = choices.get(rng.below(choices.len())) {
+            walls[cell * 4 + cur_wall] = false;
+            walls[(nr * size + nc) * 4 + nb_wall] = false;
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Racing a ghost of the player's best run.
+//!
+//! Each finished run is timed step by step against the shared game clock.
+//! The fastest run on a maze is kept under `maze_ghosts`, keyed by the seed,
+//! size and floor count that regenerate it, and replayed as a translucent
+//! ghost whenever that maze comes round again (for instance from a shared
+//! puzzle link).
+
+use super::Perception;
+use crate::games::clock;
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen::prelude::*;
+
+const GHOSTS_KEY: &str = "maze_ghosts";
+/// Best runs kept in storage; the oldest are dropped first.
+const MAX_GHOSTS: usize = 50;
+/// How often the ghost is moved on, in milliseconds.
+const GHOST_TICK_MS: i32 = 100;
+
+/// The fastest finished run on one maze.
+#[derive(Clone, Serialize, Deserialize)]
+pub(super) struct Ghost {
+    seed: u32,
+    size: usize,
+    floors: usize,
+    /// Milliseconds of game time since the run began, and the cell reached.
+    steps: Vec<(u32, (usize, usize))>,
+}
+
+impl Ghost {
+    fn finish_time(&self) -> u32 {
+        self.steps.last().map_or(0, |&(ms, _)| ms)
+    }
+
+    /// Where the ghost stands `elapsed` milliseconds into its run.
+    fn position_at(&self, elapsed: f64) -> Option<(usize, usize)> {
+        self.steps
+            .iter()
+            .take_while(|&&(ms, _)| f64::from(ms) <= elapsed)
+            .last()
+            .or_else(|| self.steps.first())
+            .map(|&(_, pos)| pos)
+    }
+}
+
+fn load_ghosts() -> Vec<Ghost> {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .and_then(|storage| storage.get_item(GHOSTS_KEY).ok().flatten())
+        .and_then(|json| js_sys::JSON::parse(&json).ok())
+        .and_then(|value| serde_wasm_bindgen::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+fn store_ghosts(ghosts: &[Ghost]) -> Result<(), JsValue> {
+    let window = web_sys::window().expect("no global window exists");
+    let storage = window.local_storage()?.expect("no local storage exists");
+    let value = serde_wasm_bindgen::to_value(ghosts)?;
+    let json = js_sys::JSON::stringify(&value)?.as_string().unwrap();
+    storage.set_item(GHOSTS_KEY, &json)
+}
+
+impl Perception {
+    fn matches(&self, ghost: &Ghost) -> bool {
+        (ghost.seed, ghost.size, ghost.floors) == (self.seed, self.size, self.floors)
+    }
+
+    /// Picks up the stored best run for the current maze, if there is one.
+    pub(super) fn load_ghost(&mut self) {
+        self.ghost = load_ghosts().into_iter().find(|ghost| self.matches(ghost));
+    }
+
+    /// Starts timing a run from the player's cell and sends the ghost back
+    /// to its start.
+    pub(super) fn begin_run(&mut self) {
+        self.run = vec![(0, self.current_position)];
+        self.run_started = clock::now();
+        // The next tick draws the ghost at its start
+        self.ghost_position = None;
+    }
+
+    pub(super) fn record_step(&mut self, pos: (usize, usize)) {
+        let elapsed = (clock::now() - self.run_started).max(0.0) as u32;
+        self.run.push((elapsed, pos));
+    }
+
+    /// Keeps the run just finished if it beats the stored best on this maze.
+    /// Runs picked up part-way, e.g. after a reload, are not timed fairly and
+    /// are skipped.
+    pub(super) fn finish_run(&mut self) -> Result<(), JsValue> {
+        if self.replaying || self.run.first().map(|&(_, pos)| pos) != Some(self.start_position) {
+            return Ok(());
+        }
+        let run = Ghost {
+            seed: self.seed,
+            size: self.size,
+            floors: self.floors,
+            steps: std::mem::take(&mut self.run),
+        };
+
+        let mut ghosts = load_ghosts();
+        if let Some(best) = ghosts.iter().position(|ghost| self.matches(ghost)) {
+            if ghosts[best].finish_time() <= run.finish_time() {
+                return Ok(());
+            }
+            ghosts.remove(best);
+        }
+        ghosts.push(run);
+        let excess = ghosts.len().saturating_sub(MAX_GHOSTS);
+        ghosts.drain(..excess);
+        store_ghosts(&ghosts)
+    }
+
+    /// Moves the ghost on to where it was at this point of its run, updating
+    /// just the cells it left and entered.
+    fn advance_ghost(&mut self) -> Result<(), JsValue> {
+        let Some(ghost) = &self.ghost else {
+            return Ok(());
+        };
+        let position = ghost.position_at(clock::now() - self.run_started);
+        if position == self.ghost_position {
+            return Ok(());
+        }
+        let previous = std::mem::replace(&mut self.ghost_position, position);
+
+        let maze = self
+            .document
+            .get_element_by_id("maze")
+            .expect("Maze element not found");
+        for (x, y) in previous.into_iter().chain(position) {
+            if let Some(cell) = self
+                .dom_index((x, y))
+                .and_then(|index| maze.children().item(index))
+            {
+                self.update_cell_state(&cell, x, y)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Shows the time to beat, or hides it when this maze has no ghost yet.
+    pub(super) fn render_ghost(&self) -> Result<(), JsValue> {
+        let Some(best) = self.document.get_element_by_id("ghost-best") else {
+            return Ok(());
+        };
+        match &self.ghost {
+            Some(ghost) => {
+                let seconds = ghost.finish_time() / 1000;
+                if let Some(time) = self.document.get_element_by_id("ghost-time") {
+                    time.set_text_content(Some(&format!("{}:{:02}", seconds / 60, seconds % 60)));
+                }
+                best.remove_attribute("hidden")
+            }
+            None => best.set_attribute("hidden", ""),
+        }
+    }
+
+    pub(super) fn setup_ghost_tick(game_state: &Rc<RefCell<Self>>) -> Result<(), JsValue> {
+        let callback = {
+            let game_state = game_state.clone();
+            Closure::wrap(Box::new(move || {
+                if let Ok(mut game) = game_state.try_borrow_mut() {
+                    game.advance_ghost().unwrap_or_else(|e| {
+                        web_sys::console::log_2(&"Failed to move ghost:".into(), &e);
+                    });
+                }
+            }) as Box<dyn FnMut()>)
+        };
+
+        web_sys::window()
+            .expect("no global window exists")
+            .set_interval_with_callback_and_timeout_and_arguments_0(
+                callback.as_ref().unchecked_ref(),
+                GHOST_TICK_MS,
+            )?;
+        callback.forget();
+        Ok(())
+    }
+}
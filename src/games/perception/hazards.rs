@@ -0,0 +1,271 @@
+use super::{review::WallHit, Perception};
+use crate::games::{clock, recording::Input, rng::Rng};
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen::prelude::*;
+
+/// Milliseconds between hazard updates.
+const HAZARD_TICK_MS: i32 = 600;
+
+/// Patrols back and forth along a fixed corridor.
+#[derive(Clone, Serialize, Deserialize)]
+pub(super) struct Sentry {
+    route: Vec<(usize, usize)>,
+}
+
+impl Sentry {
+    fn position(&self, tick: usize) -> (usize, usize) {
+        // Ping-pong over the route: 0, 1, .., n-1, n-2, .., 1, 0, ..
+        let n = self.route.len();
+        if n < 2 {
+            return self.route[0];
+        }
+        let cycle = 2 * (n - 1);
+        let step = tick % cycle;
+        self.route[if step < n { step } else { cycle - step }]
+    }
+}
+
+/// Cell that is impassable for half of every `period` ticks.
+#[derive(Clone, Serialize, Deserialize)]
+pub(super) struct Toggle {
+    position: (usize, usize),
+    period: usize,
+    phase: usize,
+}
+
+impl Toggle {
+    fn is_closed(&self, tick: usize) -> bool {
+        (tick + self.phase) % self.period >= self.period / 2
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(super) struct Hazards {
+    sentries: Vec<Sentry>,
+    toggles: Vec<Toggle>,
+    /// Cells that give way once the player steps off them.
+    collapsing: Vec<(usize, usize)>,
+
+    // Live state
+    collapsed: Vec<(usize, usize)>,
+    tick: usize,
+    /// Steps taken since the log last recorded them. Steps are logged in one
+    /// batch before the next input or collision, so an idle player adds
+    /// nothing to the log.
+    #[serde(default)]
+    unlogged_ticks: usize,
+}
+
+impl Hazards {
+    /// Sentries, toggling cells and collapsing cells for a maze of this size.
+    fn counts(size: usize) -> (usize, usize, usize) {
+        (
+            size.saturating_sub(5) / 2,
+            size.saturating_sub(6) / 2,
+            size.saturating_sub(7),
+        )
+    }
+
+    /// Places hazards on a generated maze.
+    ///
+    /// Sentries patrol corridors away from the start cell so a reset is always
+    /// safe, toggles sit on the solution path so timing matters, and collapsing
+    /// cells are limited to the stretch of path after the last objective,
+    /// which a solution only crosses once.
+    pub(super) fn generate(
+        walls: &[bool],
+        size: usize,
+        path: &[usize],
+        last_objective: usize,
+        protected: &[usize],
+        rng: &mut Rng,
+    ) -> Self {
+        let (sentries, toggles, collapsing) = Self::counts(size);
+        let to_xy = |cell: usize| (cell % size, cell / size);
+        let mut hazards = Self::default();
+        let mut taken: Vec<usize> = protected.to_vec();
+
+        for _ in 0..sentries {
+            let origin = rng.below(size * size);
+            if taken.contains(&origin) {
+                continue;
+            }
+            let mut route = vec![origin];
+            let length = 3 + rng.below(3);
+            while route.len() < length {
+                let current = *route.last().expect("route starts with a cell");
+                let open: Vec<usize> = open_neighbours(walls, size, current)
+                    .into_iter()
+                    .filter(|n| !route.contains(n) && !taken.contains(n))
+                    .collect();
+                let Some(&next) = open.get(rng.below(open.len())) else {
+                    break;
+                };
+                route.push(next);
+            }
+            if route.len() > 1 {
+                taken.extend(&route);
+                hazards.sentries.push(Sentry {
+                    route: route.into_iter().map(to_xy).collect(),
+                });
+            }
+        }
+
+        let interior = |i: usize| i > 0 && i + 1 < path.len();
+        let mut path_slots: Vec<usize> = (0..path.len())
+            .filter(|&i| interior(i) && !taken.contains(&path[i]))
+            .collect();
+        for _ in 0..toggles {
+            if path_slots.is_empty() {
+                break;
+            }
+            let cell = path[path_slots.remove(rng.below(path_slots.len()))];
+            taken.push(cell);
+            hazards.toggles.push(Toggle {
+                position: to_xy(cell),
+                period: 6 + 2 * rng.below(3),
+                phase: rng.below(6),
+            });
+        }
+
+        let mut tail: Vec<usize> = path_slots
+            .into_iter()
+            .filter(|&i| i > last_objective && !taken.contains(&path[i]))
+            .collect();
+        for _ in 0..collapsing {
+            if tail.is_empty() {
+                break;
+            }
+            let cell = path[tail.remove(rng.below(tail.len()))];
+            hazards.collapsing.push(to_xy(cell));
+        }
+
+        hazards
+    }
+
+    /// Every cell a hazard can occupy.
+    pub(super) fn cells(&self) -> Vec<(usize, usize)> {
+        self.sentries
+            .iter()
+            .flat_map(|s| s.route.iter().copied())
+            .chain(self.toggles.iter().map(|t| t.position))
+            .chain(self.collapsing.iter().copied())
+            .collect()
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.sentries.is_empty() && self.toggles.is_empty() && self.collapsing.is_empty()
+    }
+
+    /// Whether standing on `pos` right now counts as a collision.
+    pub(super) fn blocks(&self, pos: (usize, usize)) -> bool {
+        self.sentry_at(pos)
+            || self.toggle_closed_at(pos)
+            || self.collapsed.contains(&pos)
+    }
+
+    pub(super) fn sentry_at(&self, pos: (usize, usize)) -> bool {
+        self.sentries.iter().any(|s| s.position(self.tick) == pos)
+    }
+
+    pub(super) fn toggle_at(&self, pos: (usize, usize)) -> Option<bool> {
+        self.toggles
+            .iter()
+            .find(|t| t.position == pos)
+            .map(|t| t.is_closed(self.tick))
+    }
+
+    fn toggle_closed_at(&self, pos: (usize, usize)) -> bool {
+        self.toggle_at(pos).unwrap_or(false)
+    }
+
+    pub(super) fn is_collapsing(&self, pos: (usize, usize)) -> bool {
+        self.collapsing.contains(&pos)
+    }
+
+    pub(super) fn is_collapsed(&self, pos: (usize, usize)) -> bool {
+        self.collapsed.contains(&pos)
+    }
+
+    /// Called as the player steps off `pos`.
+    pub(super) fn leave(&mut self, pos: (usize, usize)) {
+        if self.collapsing.contains(&pos) && !self.collapsed.contains(&pos) {
+            self.collapsed.push(pos);
+        }
+    }
+
+    pub(super) fn reset_attempt(&mut self) {
+        self.collapsed.clear();
+    }
+}
+
+fn open_neighbours(walls: &[bool], size: usize, cell: usize) -> Vec<usize> {
+    let (r, c) = (cell / size, cell % size);
+    let mut out = Vec::new();
+    if r > 0 && !walls[cell * 4] {
+        out.push(cell - size);
+    }
+    if c + 1 < size && !walls[cell * 4 + 1] {
+        out.push(cell + 1);
+    }
+    if r + 1 < size && !walls[cell * 4 + 2] {
+        out.push(cell + size);
+    }
+    if c > 0 && !walls[cell * 4 + 3] {
+        out.push(cell - 1);
+    }
+    out
+}
+
+impl Perception {
+    /// Advances sentries and toggles by `steps`, treating a collision like a
+    /// wall hit.
+    pub(super) fn hazard_tick(&mut self, steps: usize) -> Result<(), JsValue> {
+        self.hazards.tick += steps;
+        if self.hazards.blocks(self.current_position) {
+            if self.log_hazard_ticks() {
+                self.recording.publish(&self.document);
+            }
+            self.end_attempt(Some(WallHit {
+                from: self.current_position,
+                to: self.current_position,
+            }));
+            self.reset_position();
+        }
+        self.render()
+    }
+
+    /// Logs the hazard steps taken since the last entry, if any. Returns
+    /// whether the log changed.
+    pub(super) fn log_hazard_ticks(&mut self) -> bool {
+        let steps = std::mem::take(&mut self.hazards.unlogged_ticks);
+        steps > 0 && !self.replaying && self.recording.record(Input::Tick(steps))
+    }
+
+    pub(super) fn setup_hazard_tick(game_state: &Rc<RefCell<Self>>) -> Result<(), JsValue> {
+        let callback = {
+            let game_state = game_state.clone();
+            Closure::wrap(Box::new(move || {
+                if let Ok(mut game) = game_state.try_borrow_mut() {
+                    if game.hazards.is_empty() || !clock::is_running() {
+                        return;
+                    }
+                    game.hazards.unlogged_ticks += 1;
+                    game.hazard_tick(1).unwrap_or_else(|e| {
+                        web_sys::console::log_2(&"Failed to update hazards:".into(), &e);
+                    });
+                }
+            }) as Box<dyn FnMut()>)
+        };
+
+        web_sys::window()
+            .expect("no global window exists")
+            .set_interval_with_callback_and_timeout_and_arguments_0(
+                callback.as_ref().unchecked_ref(),
+                HAZARD_TICK_MS,
+            )?;
+        callback.forget();
+        Ok(())
+    }
+}
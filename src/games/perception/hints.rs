@@ -0,0 +1,62 @@
+use super::Perception;
+use crate::games::{clock, recording::Input};
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen::prelude::*;
+
+/// Hints available on each maze.
+pub(super) const HINTS_PER_MAZE: usize = 3;
+/// Moves added to the count for every hint taken.
+pub(super) const HINT_PENALTY: usize = 5;
+
+impl Perception {
+    /// Points out the next step towards the exit, spending one hint and
+    /// logging it only if there is one to spend.
+    pub(super) fn take_hint(&mut self) -> Result<(), JsValue> {
+        if self.hints_left == 0 || self.hint.is_some() {
+            return Ok(());
+        }
+        let Some(&next) = self.solve().as_ref().and_then(|steps| steps.first()) else {
+            return Ok(());
+        };
+        self.hint = Some(next);
+        self.hints_left -= 1;
+        self.hints_used += 1;
+        self.moves += HINT_PENALTY;
+        self.record_input(Input::Hint);
+        self.render()
+    }
+
+    pub(super) fn render_hint_button(&self) -> Result<(), JsValue> {
+        if let Some(button) = self.document.get_element_by_id("hint") {
+            button.set_text_content(Some(&format!("Hint ({})", self.hints_left)));
+            if self.hints_left == 0 {
+                button.set_attribute("disabled", "")?;
+            } else {
+                button.remove_attribute("disabled")?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn setup_hint_handler(game_state: &Rc<RefCell<Self>>) -> Result<(), JsValue> {
+        let Some(button) = game_state.borrow().document.get_element_by_id("hint") else {
+            return Ok(());
+        };
+        let handler = {
+            let game_state = game_state.clone();
+            Closure::wrap(Box::new(move |_event: web_sys::MouseEvent| {
+                if !clock::is_running() {
+                    return;
+                }
+                if let Ok(mut game) = game_state.try_borrow_mut() {
+                    game.take_hint().unwrap_or_else(|e| {
+                        web_sys::console::log_2(&"Failed to show hint:".into(), &e);
+                    });
+                }
+            }) as Box<dyn FnMut(_)>)
+        };
+        button.add_event_listener_with_callback("click", handler.as_ref().unchecked_ref())?;
+        handler.forget();
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use super::Perception;
+use crate::games::{clock, recording::Input};
 use std::{cell::RefCell, rc::Rc};
 use wasm_bindgen::prelude::*;
 use web_sys::Element;
@@ -8,6 +9,9 @@ impl Perception {
         let click_handler = {
             let game_state = game_state.clone();
             Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
+                if !clock::is_running() {
+                    return;
+                }
                 if let Ok(mut game) = game_state.try_borrow_mut() {
                     if let Some(target) = event.target() {
                         if let Some(element) = target.dyn_ref::<Element>() {
@@ -25,12 +29,10 @@ impl Perception {
 
                                 let size = game.size;
                                 let x = cell_index % size;
-                                let y = cell_index / size;
+                                let y = cell_index / size + game.current_floor() * size;
 
-                                let result = game.try_move(x, y);
-                                if result != 0 {
-                                    game.render().unwrap();
-                                }
+                                game.record_input(Input::Cell(x, y));
+                                game.apply_input(Input::Cell(x, y)).unwrap();
                             }
                         }
                     }
@@ -0,0 +1,52 @@
+//! Perception's level schedule in `levels.json`: each level's maze size,
+//! generator, braiding, keys, time limit and wall reveal. The last row
+//! stops growing the maze, so it never outgrows the screen.
+
+use super::{generators::Generator, reveal::RevealMode};
+use crate::games::schedule;
+use serde::Deserialize;
+use std::sync::LazyLock;
+
+static SCHEDULE: LazyLock<Vec<Stage>> =
+    LazyLock::new(|| schedule::schedule(include_str!("levels.json")));
+
+fn default_seconds() -> u32 {
+    300
+}
+
+/// One row of the schedule.
+#[derive(Clone, Debug, Deserialize)]
+pub(super) struct Stage {
+    /// Cells along each side of a floor.
+    pub(super) size: usize,
+    #[serde(default)]
+    pub(super) generator: Generator,
+    /// Share of dead ends opened into loops, from 0 (a perfect maze) to 1.
+    #[serde(default)]
+    pub(super) braid: f64,
+    /// Coloured key and door pairs, on top of the key to the exit.
+    #[serde(default)]
+    pub(super) keys: usize,
+    /// Time limit in seconds.
+    #[serde(default = "default_seconds")]
+    pub(super) seconds: u32,
+    /// How walls are shown unless the player has picked another mode.
+    #[serde(default)]
+    pub(super) reveal: RevealMode,
+}
+
+impl Stage {
+    /// A stage like level 1's but `size` cells across, for mazes loaded
+    /// from elsewhere.
+    pub(super) fn sized(size: usize) -> Self {
+        Self {
+            size,
+            ..stage(1).clone()
+        }
+    }
+}
+
+/// The maze settings for `level`, counted from 1.
+pub(super) fn stage(level: usize) -> &'static Stage {
+    schedule::stage(&SCHEDULE, level)
+}
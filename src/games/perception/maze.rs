@@ -1,75 +1,31 @@
+use super::generators::{braid, DIRECTIONS};
+use super::hazards::Hazards;
+use super::hints::HINTS_PER_MAZE;
+use super::levels::Stage;
+use super::objectives::{branch_points, Objectives};
+use crate::games::{recording::Recording, rng::Rng};
 use crate::Perception;
 use js_sys::Date;
-use js_sys::Math;
 use std::collections::{HashSet, VecDeque};
 use web_sys::Document;
 
 impl Perception {
-    pub(super) fn create_maze(size: usize, document: Document) -> Self {
+    pub(super) fn create_maze(stage: &Stage, seed: u32, document: Document) -> Self {
+        let mut rng = Rng::new(seed);
+        let size = stage.size;
+
         // Total cells and walls per cell (top, right, bottom, left)
         let total_cells = size * size;
         let wall_per_cell = 4;
-        let mut walls = vec![true; total_cells * wall_per_cell];
-
-        // DFS setup for spanning tree generation
-        let mut visited_cells = vec![false; total_cells];
         let idx = |r: usize, c: usize| r * size + c; // utility: (row, col) -> index
 
         // Pick a random starting cell (row, col)
-        let start_row = (Math::random() * size as f64).floor() as usize;
-        let start_col = (Math::random() * size as f64).floor() as usize;
-
-        let mut stack = vec![(start_row, start_col)];
-        visited_cells[idx(start_row, start_col)] = true;
-
-        // Directions: (dr, dc, current wall index, neighbor wall index)
-        // Up: (r-1, c) uses wall 0 in current and 2 in neighbor.
-        // Right: (r, c+1) uses wall 1 in current and 3 in neighbor.
-        // Down: (r+1, c) uses wall 2 in current and 0 in neighbor.
-        // Left: (r, c-1) uses wall 3 in current and 1 in neighbor.
-        let directions = [
-            (-1, 0, 0, 2),
-            (0, 1, 1, 3),
-            (1, 0, 2, 0),
-            (0, -1, 3, 1),
-        ];
-
-        // Iterative DFS: remove walls to create a spanning tree
-        while let Some((r, c)) = stack.last().copied() {
-            let mut neighbors = Vec::new();
-            for &(dr, dc, cur_wall, nb_wall) in &directions {
-                let nr = r as isize + dr;
-                let nc = c as isize + dc;
-                if nr >= 0 && nr < size as isize && nc >= 0 && nc < size as isize {
-                    let nr = nr as usize;
-                    let nc = nc as usize;
-                    if !visited_cells[idx(nr, nc)] {
-                        neighbors.push((nr, nc, cur_wall, nb_wall));
-                    }
-                }
-            }
+        let start_row = (rng.random() * size as f64).floor() as usize;
+        let start_col = (rng.random() * size as f64).floor() as usize;
 
-            if neighbors.is_empty() {
-                stack.pop();
-            } else {
-                // Shuffle neighbors using js_sys::Math::random
-                neighbors.sort_by(|_, _| {
-                    if Math::random() < 0.5 {
-                        std::cmp::Ordering::Less
-                    } else {
-                        std::cmp::Ordering::Greater
-                    }
-                });
-                let (nr, nc, cur_wall, nb_wall) = neighbors[0];
-                // Remove walls between current and neighbor
-                let cell_base = idx(r, c) * wall_per_cell;
-                walls[cell_base + cur_wall] = false;
-                let nb_base = idx(nr, nc) * wall_per_cell;
-                walls[nb_base + nb_wall] = false;
-                visited_cells[idx(nr, nc)] = true;
-                stack.push((nr, nc));
-            }
-        }
+        // Carve a spanning tree, then open some dead ends into loops
+        let mut walls = stage.generator.carve(size, (start_row, start_col), &mut rng);
+        braid(&mut walls, size, stage.braid, &mut rng);
 
         // --- BFS helper: find furthest cell (and parent pointers) ---
         let bfs = |start_cell: (usize, usize)| {
@@ -83,7 +39,7 @@ impl Perception {
             while let Some(current) = queue.pop_front() {
                 let r = current / size;
                 let c = current % size;
-                for &(dr, dc, cur_wall, _) in &directions {
+                for &(dr, dc, cur_wall, _) in &DIRECTIONS {
                     let nr = r as isize + dr;
                     let nc = c as isize + dc;
                     if nr >= 0 && nr < size as isize && nc >= 0 && nc < size as isize {
@@ -132,7 +88,7 @@ impl Perception {
         let door_rc = cell_b;
         let key_rc = if path.len() >= 3 {
             // Pick a random intermediate index (excluding endpoints)
-            let key_idx = 1 + ((Math::random() * ((path.len() - 2) as f64)).floor() as usize);
+            let key_idx = 1 + ((rng.random() * ((path.len() - 2) as f64)).floor() as usize);
             let cell = path[key_idx];
             (cell / size, cell % size)
         } else {
@@ -143,11 +99,23 @@ impl Perception {
         let door_cell = convert(door_rc);
         let key_cell  = convert(key_rc);
 
+        // --- Coloured keys/doors and waypoints for larger mazes ---
+        let reserved = [idx(start_rc.0, start_rc.1), idx(key_rc.0, key_rc.1), idx(door_rc.0, door_rc.1)];
+        let objectives =
+            Objectives::generate(&path, &parent_map, size, stage.keys, &reserved, &mut rng);
+
+        // --- Moving hazards, kept clear of every objective ---
+        let mut protected = objectives.cells(size);
+        protected.extend(reserved);
+        let branch = branch_points(&path, &parent_map, size);
+        let last_objective = protected.iter().map(|&cell| branch[cell]).max().unwrap_or(0);
+        let hazards = Hazards::generate(&walls, size, &path, last_objective, &protected, &mut rng);
+
         // --- Initialize remaining fields ---
         let mut visited = HashSet::new();
         visited.insert(start_cell);
 
-        Self {
+        let mut game = Self {
             document,
             size,
             walls,
@@ -157,10 +125,38 @@ impl Perception {
             door_position: door_cell,
             visited,
             has_key: false,
+            objectives,
+            hazards,
+            teleporters: Vec::new(),
+            floors: 1,
+            stairs: Vec::new(),
+            mode: None,
+            discovered: Vec::new(),
+            preview_until: 0.0,
+            completions: Vec::new(),
+            hints_left: HINTS_PER_MAZE,
+            hint: None,
+            hints_used: 0,
+            run: Vec::new(),
+            run_started: 0.0,
+            ghost: None,
+            ghost_position: None,
+            seed,
+            rng,
+            recording: Recording::new('p', seed, 1),
+            replaying: false,
+            path: vec![start_cell],
+            attempts: Vec::new(),
+            review: None,
             level: 1,
             moves: 0,
-            time_remaining: 300,
+            time_remaining: stage.seconds as i32,
+            untimed: false,
             last_tick: Date::now() / 1000.0,
-        }
+        };
+
+        // --- One-way passages and teleporters, checked by the solver ---
+        game.add_passages();
+        game
     }
 }
@@ -1,10 +1,30 @@
+mod difficulty;
+mod export;
+mod floors;
+mod ghost;
+mod hazards;
+mod generators;
+mod hints;
 mod input;
+mod levels;
 mod maze;
 mod movement;
+mod objectives;
+mod passages;
 mod render;
+mod replay;
+mod reveal;
+mod review;
+mod solver;
 mod state;
 mod timer;
 
+use crate::games::{
+    clock,
+    settings,
+    recording::{Input, Recording},
+    rng::{random_seed, Rng},
+};
 use serde::{Deserialize, Serialize};
 use std::{cell::RefCell, collections::HashSet, rc::Rc};
 use wasm_bindgen::prelude::*;
@@ -27,6 +47,9 @@ pub struct Perception {
     moves: usize,  // <-- New field to record moves
 
     // Maze elements
+    /// `walls[cell * 4 + side]` (sides: top, right, bottom, left) blocks
+    /// leaving `cell` through `side`. A passage open from one side only is
+    /// one-way.
     walls: Vec<bool>,
     current_position: (usize, usize),
     start_position: (usize, usize),
@@ -34,15 +57,85 @@ pub struct Perception {
     door_position: (usize, usize),
     visited: HashSet<(usize, usize)>,
     has_key: bool,
+    #[serde(default)]
+    objectives: objectives::Objectives,
+    #[serde(default)]
+    hazards: hazards::Hazards,
+    /// Paired cells: stepping onto one lands the player on the other.
+    #[serde(default)]
+    teleporters: Vec<((usize, usize), (usize, usize))>,
+    /// Floors stacked in `walls`, each `size` rows tall.
+    #[serde(default = "floors::default_floors")]
+    floors: usize,
+    /// Cells with stairs up to the cell directly above them.
+    #[serde(default)]
+    stairs: Vec<(usize, usize)>,
+
+    // Which walls are shown, those found so far and the completed mazes
+    /// The player's wall mode, or `None` for the level's own rule.
+    #[serde(default)]
+    mode: Option<reveal::RevealMode>,
+    #[serde(default)]
+    discovered: Vec<usize>,
+    /// Game time the preview ends. The clock starts again with the page, so
+    /// a reload ends the preview rather than restoring it.
+    #[serde(skip)]
+    preview_until: f64,
+    #[serde(default)]
+    completions: Vec<reveal::Completion>,
+
+    // Hints left on this maze, the step currently pointed out and the hints
+    // taken on this attempt
+    #[serde(default = "default_hints")]
+    hints_left: usize,
+    #[serde(default)]
+    hint: Option<(usize, usize)>,
+    #[serde(default)]
+    hints_used: usize,
+
+    // This run's timed steps and the best run on this maze, raced as a ghost
+    #[serde(skip)]
+    run: Vec<(u32, (usize, usize))>,
+    #[serde(skip)]
+    run_started: f64,
+    #[serde(skip)]
+    ghost: Option<ghost::Ghost>,
+    #[serde(skip)]
+    ghost_position: Option<(usize, usize)>,
 
-    // Timer state
+    // Attempt history for the wall-hit heatmap and replay
+    #[serde(default)]
+    path: Vec<(usize, usize)>,
+    #[serde(default)]
+    attempts: Vec<review::Attempt>,
+    #[serde(default)]
+    review: Option<review::Review>,
+
+    // Seed of the current maze, the generator for the next one and the
+    // maze's input log
+    #[serde(default)]
+    seed: u32,
+    #[serde(default)]
+    rng: Rng,
+    #[serde(default)]
+    recording: Recording,
+    #[serde(skip)]
+    replaying: bool,
+
+    // Timer state; untimed mazes never run out
     time_remaining: i32,
+    #[serde(default)]
+    untimed: bool,
     last_tick: f64,
 
     #[serde(skip, default = "get_document")]
     document: Document,
 }
 
+fn default_hints() -> usize {
+    hints::HINTS_PER_MAZE
+}
+
 #[wasm_bindgen]
 impl Perception {
     #[wasm_bindgen(constructor)]
@@ -53,35 +146,17 @@ impl Perception {
             .local_storage()?
             .expect("no local storage");
 
-        let now = js_sys::Date::now();
         let mut game = if let Some(state) = storage.get_item("maze_state")? {
-            let last_save = storage
-                .get_item("maze_time")?
-                .unwrap_or_else(|| "0".to_string())
-                .parse::<f64>()
-                .unwrap_or(0.0);
-
-            // If more than 5 minutes (300_000 ms) have passed since last save, create a new maze.
-            if now - last_save > 300_000.0 {
-                Self::create_maze(2, document)
-            } else {
-                // Deserialize the saved state.
-                let mut game: Self =
-                    serde_wasm_bindgen::from_value(js_sys::JSON::parse(&state)?)?;
-                let now_secs = now / 1000.0;
-                let elapsed = now_secs - game.last_tick;
-                if elapsed as i32 >= game.time_remaining {
-                    // Timer expired: start a new maze.
-                    Self::create_maze(2, document)
-                } else {
-                    // Otherwise adjust the time remaining based on elapsed time.
-                    game.time_remaining -= elapsed as i32;
-                    game.last_tick = now_secs;
-                    game
-                }
-            }
+            // The clock was stopped while the page was away, so the saved
+            // time remaining still stands.
+            let mut game: Self = serde_wasm_bindgen::from_value(js_sys::JSON::parse(&state)?)?;
+            game.last_tick = js_sys::Date::now() / 1000.0;
+            game
         } else {
-            Self::create_maze(2, document)
+            let mut game = Self::graded_maze(levels::stage(1), 1, random_seed(), &document);
+            game.begin_reveal();
+            game.restart_timer();
+            game
         };
 
         game.render()?;
@@ -90,16 +165,30 @@ impl Perception {
     }
     #[wasm_bindgen]
     pub fn start(&mut self) -> Result<(), JsValue> {
+        clock::install("maze_paused")?;
+        settings::install('p')?;
+        self.load_ghost();
+        self.begin_run();
+        self.render_ghost()?;
+        self.recording.publish(&self.document);
+
         let game_state = Rc::new(RefCell::new(self.clone()));
 
         Self::setup_click_handler(game_state.clone())?;
         Self::setup_timer(&game_state)?;
+        Self::setup_hazard_tick(&game_state)?;
+        Self::setup_ghost_tick(&game_state)?;
+        Self::setup_review_handlers(&game_state)?;
+        Self::setup_mode_handlers(&game_state)?;
+        Self::setup_hint_handler(&game_state)?;
+        Self::setup_floor_handlers(&game_state)?;
 
         // Set up reset button handler
         if let Some(reset_btn) = self.document.get_element_by_id("reset-level") {
             let game_state = game_state.clone();
             let handler = Closure::wrap(Box::new(move |_event: web_sys::MouseEvent| {
                 if let Ok(mut game) = game_state.try_borrow_mut() {
+                    game.record_input(Input::Reset);
                     game.reset_to_level_one().unwrap();
                 }
             }) as Box<dyn FnMut(_)>);
@@ -114,31 +203,53 @@ impl Perception {
     }
     #[wasm_bindgen]
     pub fn reset(&mut self) {
-        let new_game = Self::create_maze(self.size, self.document.clone());
-        self.walls = new_game.walls;
-        self.key_position = new_game.key_position;
-        self.door_position = new_game.door_position;
+        self.next_maze();
         self.reset_position();
+        self.attempts.clear();
 
         // Reset timer state completely
         self.moves = 0;
-        self.time_remaining = 300;
-        self.last_tick = js_sys::Date::now() / 1000.0;
+        self.restart_timer();
 
         // Force timer display update
-        if let Some(timer_el) = self.document.get_element_by_id("timer") {
-            timer_el.set_text_content(Some("5:00"));
-        }
+        self.update_timer_display();
 
         // Update display
         self.render().expect("Failed to render reset");
     }
+    /// Swaps in a freshly generated maze for the current level.
+    fn next_maze(&mut self) {
+        let seed = self.rng.next_u32();
+        let stage = levels::stage(self.level);
+        let new_game = Self::graded_maze(stage, self.floors, seed, &self.document);
+        self.size = stage.size;
+        self.seed = new_game.seed;
+        self.walls = new_game.walls;
+        self.key_position = new_game.key_position;
+        self.door_position = new_game.door_position;
+        self.objectives = new_game.objectives;
+        self.hazards = new_game.hazards;
+        self.teleporters = new_game.teleporters;
+        self.stairs = new_game.stairs;
+        self.start_position = new_game.start_position;
+        self.hints_left = hints::HINTS_PER_MAZE;
+        self.begin_reveal();
+        self.load_ghost();
+        // Each maze gets its own log, replayed from the maze's own seed. The
+        // link keeps pointing at the last maze until the first input here
+        self.recording = Recording::new('p', self.seed, self.level).variant(self.floors);
+    }
     fn reset_position(&mut self) {
         let old_pos = self.current_position;
         self.current_position = self.start_position;
         self.visited.clear();
         self.visited.insert(self.start_position);
+        self.path = vec![self.start_position];
+        self.hint = None;
         self.has_key = false;
+        self.objectives.reset_progress();
+        self.hazards.reset_attempt();
+        self.begin_run();
 
         // Update only the changed cells rather than the entire grid.
         let maze = self
@@ -148,8 +259,10 @@ impl Perception {
 
         // Helper closure to update a specific cell.
         let update_cell = |x: usize, y: usize| -> Result<(), JsValue> {
-            let index = (y * self.size + x) as u32;
-            if let Some(cell) = maze.children().item(index) {
+            if let Some(cell) = self
+                .dom_index((x, y))
+                .and_then(|index| maze.children().item(index))
+            {
                 self.update_cell_state(&cell, x, y)?;
             }
             Ok(())
@@ -164,20 +277,16 @@ impl Perception {
     #[wasm_bindgen]
     pub fn reset_to_level_one(&mut self) -> Result<(), JsValue> {
         // Only reset if above level 1
-        if self.size > 2 {
-            self.size = 2; // Level 1 starts with size 2
+        if self.level > 1 {
             self.level = 1;
 
             // Create new level 1 maze
-            let new_game = Self::create_maze(self.size, self.document.clone());
-            self.walls = new_game.walls;
-            self.key_position = new_game.key_position;
-            self.door_position = new_game.door_position;
+            self.next_maze();
 
             // Reset position and timer
             self.reset_position();
-            self.time_remaining = 300;
-            self.last_tick = js_sys::Date::now() / 1000.0;
+            self.attempts.clear();
+            self.restart_timer();
 
             // Update displays
             if let Some(level_el) = self.document.get_element_by_id("level") {
@@ -186,9 +295,7 @@ impl Perception {
             if let Some(el) = self.document.get_element_by_id("moves") {
                 el.set_text_content(Some("0"));
             }
-            if let Some(timer_el) = self.document.get_element_by_id("timer") {
-                timer_el.set_text_content(Some("5:00"));
-            }
+            self.update_timer_display();
 
             // Show/hide reset button based on level
             if let Some(reset_btn) = self.document.get_element_by_id("reset-level") {
@@ -1,4 +1,5 @@
-use super::Perception;
+use super::{reveal::Completion, review::WallHit, Perception};
+use crate::games::share::Puzzle;
 use wasm_bindgen::prelude::*;
 
 impl Perception {
@@ -45,8 +46,8 @@ impl Perception {
 
     fn animate_wall_hit(&self, target_x: usize, target_y: usize) -> Result<(), JsValue> {
         let maze = self.document.get_element_by_id("maze").unwrap();
-        let index = self.current_position.1 * self.size + self.current_position.0;
-        if let Some(cell) = maze.children().item(index as u32) {
+        let index = self.dom_index(self.current_position);
+        if let Some(cell) = index.and_then(|index| maze.children().item(index)) {
             // Determine which border to animate.
             let border_prop = if target_x > self.current_position.0 {
                 "borderRight"
@@ -86,8 +87,7 @@ impl Perception {
                 &JsValue::from_str("0px solid transparent"),
             )?;
             keyframes.push(&end_frame);
-            let anim = cell.animate_with_f64(Some(&keyframes), 1000.0);
-            web_sys::console::log_2(&anim, &keyframes);
+            cell.animate_with_f64(Some(&keyframes), 1000.0);
         }
         Ok(())
     }
@@ -99,51 +99,103 @@ impl Perception {
 
         let wall_idx = self.get_wall_index(self.current_position.0, self.current_position.1, x, y);
 
-        // Block access to door position if key not collected
-        if (x, y) == self.door_position && !self.has_key {
+        if self.is_locked((x, y)) {
             return 0;
         }
 
         if self.walls[wall_idx] {
             // Animate the wall hit before resetting position.
             let _ = self.animate_wall_hit(x, y);
+            self.discover_wall(wall_idx);
+            self.end_attempt(Some(WallHit {
+                from: self.current_position,
+                to: (x, y),
+            }));
             self.reset_position();
             return -1;
         }
 
+        // Sentries, closed toggles and collapsed cells count as walls too
+        if self.hazards.blocks((x, y)) {
+            let _ = self.animate_wall_hit(x, y);
+            self.end_attempt(Some(WallHit {
+                from: self.current_position,
+                to: (x, y),
+            }));
+            self.reset_position();
+            return -1;
+        }
+        self.hazards.leave(self.current_position);
+        self.step_to(x, y)
+    }
+
+    /// Whether `pos` is a door the player cannot open yet.
+    pub(super) fn is_locked(&self, pos: (usize, usize)) -> bool {
+        // Block access to door position until the key and every waypoint are done
+        if pos == self.door_position && !(self.has_key && self.objectives.waypoints_done()) {
+            return true;
+        }
+
+        // Coloured doors stay shut until their key has been collected
+        !self.objectives.can_enter(pos)
+    }
+
+    /// Moves the player onto `(x, y)`, already known to be open, and collects
+    /// whatever is there: 2 if that completes the maze, otherwise 1.
+    pub(super) fn step_to(&mut self, x: usize, y: usize) -> i32 {
         // Record the move before updating the position
         self.moves += 1; // <-- Increment move counter
 
         self.current_position = (x, y);
         self.visited.insert((x, y));
+        self.path.push((x, y));
+        self.hint = None;
+
+        // Teleporters carry the player straight on to their partner
+        let landing = self.landing((x, y));
+        if landing != (x, y) {
+            self.current_position = landing;
+            self.visited.insert(landing);
+            self.path.push(landing);
+        }
+        let (x, y) = landing;
+        self.record_step(landing);
 
         if (x, y) == self.key_position {
             self.has_key = true;
-            // When key is collected, make door accessible
-            let door_x = self.door_position.0;
-            let door_y = self.door_position.1;
-            let base_idx = (door_y * self.size + door_x) * 4;
-            for i in 0..4 {
-                self.walls[base_idx + i] = false;
-            }
         }
-
-        if (x, y) == self.door_position && self.has_key {
-            // Simplified level up - increase size immediately
-            self.size += 1;
-            self.level += 1;
-            let new_game = Self::create_maze(self.size, self.document.clone());
-            self.walls = new_game.walls;
-            self.current_position = new_game.start_position; // Use start_position from new maze
-            self.start_position = new_game.start_position; // Also update start_position
-            self.key_position = new_game.key_position;
-            self.door_position = new_game.door_position;
+        self.objectives.visit((x, y));
+
+        if (x, y) == self.door_position {
+            self.capture_review();
+            let _ = self.render_review();
+
+            self.completions.push(Completion {
+                level: self.level,
+                mode: self.reveal_mode(),
+                moves: self.moves,
+                difficulty: self.metrics().map_or(0.0, |m| m.score()),
+            });
+            Puzzle::new('p', self.seed, self.level)
+                .variant(self.floors)
+                .solved(self.moves, self.elapsed_seconds())
+                .publish(&self.document);
+            let _ = self.finish_run();
+
+            // Level up; the schedule decides what the next maze looks like.
+            // Practice runs replay the same level on a fresh maze.
+            if self.reveal_mode().advances_level() {
+                self.level += 1;
+            }
+            self.next_maze();
+            self.current_position = self.start_position; // Use start_position from new maze
             self.visited.clear();
-            self.visited.insert(new_game.start_position); // Insert correct start position
+            self.visited.insert(self.start_position); // Insert correct start position
+            self.path = vec![self.start_position];
             self.has_key = false;
+            self.begin_run();
             self.moves = 0;
-            self.time_remaining = 300;
-            self.last_tick = js_sys::Date::now() / 1000.0;
+            self.restart_timer();
             return 2;
         }
         1
@@ -0,0 +1,206 @@
+use crate::games::rng::Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) enum Colour {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+}
+
+impl Colour {
+    const ALL: [Colour; 4] = [Colour::Red, Colour::Green, Colour::Blue, Colour::Yellow];
+
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            Colour::Red => "red",
+            Colour::Green => "green",
+            Colour::Blue => "blue",
+            Colour::Yellow => "yellow",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(super) struct Keyed {
+    pub(super) position: (usize, usize),
+    pub(super) colour: Colour,
+}
+
+/// Coloured keys and doors plus ordered waypoints that must all be dealt
+/// with before the exit door opens.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(super) struct Objectives {
+    pub(super) keys: Vec<Keyed>,
+    pub(super) doors: Vec<Keyed>,
+    pub(super) waypoints: Vec<(usize, usize)>,
+
+    // Progress on the current attempt
+    pub(super) collected: Vec<Colour>,
+    pub(super) next_waypoint: usize,
+}
+
+impl Objectives {
+    /// Waypoints for a maze of this size.
+    fn waypoint_count(size: usize) -> usize {
+        (size.saturating_sub(4) / 2).min(3)
+    }
+
+    /// Places `keys` coloured key/door pairs and the waypoints on a maze.
+    ///
+    /// `path` is the solution from start to exit as cell indices and `parent`
+    /// the BFS tree rooted at the start. Each coloured door sits on the path
+    /// and its key hangs off the path before it, so the doors can always be
+    /// opened in order; waypoints are never behind the exit, so any visiting
+    /// order is reachable.
+    pub(super) fn generate(
+        path: &[usize],
+        parent: &[Option<usize>],
+        size: usize,
+        keys: usize,
+        reserved: &[usize],
+        rng: &mut Rng,
+    ) -> Self {
+        let pairs = keys.min(Colour::ALL.len());
+        let waypoint_count = Self::waypoint_count(size);
+        let to_xy = |cell: usize| (cell % size, cell / size);
+        let mut taken: Vec<usize> = reserved.to_vec();
+
+        let branch = branch_points(path, parent, size);
+
+        // Doors on interior path cells, in path order
+        let mut door_slots: Vec<usize> = (1..path.len().saturating_sub(1))
+            .filter(|&i| !taken.contains(&path[i]))
+            .collect();
+        let mut door_indices = Vec::new();
+        while door_indices.len() < pairs && !door_slots.is_empty() {
+            let pick = door_slots.remove(rng.below(door_slots.len()));
+            door_indices.push(pick);
+        }
+        door_indices.sort_unstable();
+
+        let mut objectives = Self::default();
+        for (colour, &door_at) in Colour::ALL.iter().copied().zip(&door_indices) {
+            // Prefer side branches so keys take a detour, falling back to the path
+            let candidates: Vec<usize> = (0..size * size)
+                .filter(|&cell| branch[cell] < door_at && !taken.contains(&cell))
+                .filter(|cell| !path.contains(cell))
+                .collect();
+            let candidates = if candidates.is_empty() {
+                path[..door_at]
+                    .iter()
+                    .copied()
+                    .filter(|cell| !taken.contains(cell))
+                    .collect()
+            } else {
+                candidates
+            };
+            let Some(&key_cell) = candidates.get(rng.below(candidates.len())) else {
+                continue;
+            };
+            taken.push(key_cell);
+            taken.push(path[door_at]);
+            objectives.keys.push(Keyed {
+                position: to_xy(key_cell),
+                colour,
+            });
+            objectives.doors.push(Keyed {
+                position: to_xy(path[door_at]),
+                colour,
+            });
+        }
+
+        let exit = path.last().copied().unwrap_or(0);
+        let mut free: Vec<usize> = (0..size * size)
+            .filter(|&cell| cell != exit && !taken.contains(&cell))
+            .collect();
+        for _ in 0..waypoint_count {
+            if free.is_empty() {
+                break;
+            }
+            let cell = free.remove(rng.below(free.len()));
+            objectives.waypoints.push(to_xy(cell));
+        }
+
+        objectives
+    }
+
+    /// Every cell holding an objective, as `y * size + x` indices.
+    pub(super) fn cells(&self, size: usize) -> Vec<usize> {
+        self.keys
+            .iter()
+            .chain(&self.doors)
+            .map(|k| k.position)
+            .chain(self.waypoints.iter().copied())
+            .map(|(x, y)| y * size + x)
+            .collect()
+    }
+
+    pub(super) fn reset_progress(&mut self) {
+        self.collected.clear();
+        self.next_waypoint = 0;
+    }
+
+    /// Whether the player may step onto `pos`, i.e. it is not a locked door.
+    pub(super) fn can_enter(&self, pos: (usize, usize)) -> bool {
+        self.doors
+            .iter()
+            .filter(|door| door.position == pos)
+            .all(|door| self.collected.contains(&door.colour))
+    }
+
+    /// Picks up a key or ticks off the next waypoint at `pos`.
+    pub(super) fn visit(&mut self, pos: (usize, usize)) {
+        for key in &self.keys {
+            if key.position == pos && !self.collected.contains(&key.colour) {
+                self.collected.push(key.colour);
+            }
+        }
+        if self.waypoints.get(self.next_waypoint) == Some(&pos) {
+            self.next_waypoint += 1;
+        }
+    }
+
+    pub(super) fn waypoints_done(&self) -> bool {
+        self.next_waypoint >= self.waypoints.len()
+    }
+
+    pub(super) fn key_at(&self, pos: (usize, usize)) -> Option<Colour> {
+        self.keys
+            .iter()
+            .find(|key| key.position == pos && !self.collected.contains(&key.colour))
+            .map(|key| key.colour)
+    }
+
+    pub(super) fn door_at(&self, pos: (usize, usize)) -> Option<Colour> {
+        self.doors
+            .iter()
+            .find(|door| door.position == pos)
+            .map(|door| door.colour)
+    }
+
+    /// 1-based order of the waypoint at `pos`, if there is one.
+    pub(super) fn waypoint_at(&self, pos: (usize, usize)) -> Option<usize> {
+        self.waypoints.iter().position(|&w| w == pos).map(|i| i + 1)
+    }
+}
+
+/// Path index at which each cell's branch of the tree joins the solution path.
+pub(super) fn branch_points(path: &[usize], parent: &[Option<usize>], size: usize) -> Vec<usize> {
+    let mut branch = vec![0; size * size];
+    for (cell, slot) in branch.iter_mut().enumerate() {
+        let mut current = cell;
+        loop {
+            if let Some(i) = path.iter().position(|&p| p == current) {
+                *slot = i;
+                break;
+            }
+            match parent[current] {
+                Some(p) => current = p,
+                None => break,
+            }
+        }
+    }
+    branch
+}
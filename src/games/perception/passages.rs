@@ -0,0 +1,164 @@
+use super::Perception;
+use std::collections::{HashSet, VecDeque};
+
+impl Perception {
+    /// One-way passages and teleporter pairs for a maze of this size.
+    fn passage_counts(size: usize) -> (usize, usize) {
+        (
+            size.saturating_sub(6) / 2,
+            (size.saturating_sub(5) / 2).min(3),
+        )
+    }
+
+    /// Adds loops, one-way passages and teleporters to a freshly generated
+    /// maze, keeping each change only if the maze stays solvable the way its
+    /// objectives and hazards were placed for.
+    pub(super) fn add_passages(&mut self) {
+        let (one_way, teleporters) = Self::passage_counts(self.size);
+        if one_way == 0 && teleporters == 0 {
+            return;
+        }
+
+        // Cells that must keep their meaning
+        let mut protected = vec![self.start_position, self.key_position, self.door_position];
+        protected.extend(
+            self.objectives
+                .cells(self.size)
+                .into_iter()
+                .map(|cell| (cell % self.size, cell / self.size)),
+        );
+        protected.extend(self.hazards.cells());
+
+        for _ in 0..teleporters {
+            let free: Vec<(usize, usize)> = (0..self.size * self.size)
+                .map(|cell| (cell % self.size, cell / self.size))
+                .filter(|pos| !protected.contains(pos))
+                .collect();
+            if free.len() < 2 {
+                break;
+            }
+            let a = free[self.rng.below(free.len())];
+            let b = free[self.rng.below(free.len())];
+            if a == b {
+                continue;
+            }
+            self.teleporters.push((a, b));
+            if self.keeps_layout() {
+                protected.extend([a, b]);
+            } else {
+                self.teleporters.pop();
+            }
+        }
+
+        // A one-way edge in a perfect maze always cuts something off, so open
+        // a few extra passages first to give the maze loops.
+        for _ in 0..one_way * 2 {
+            let (x, y) = (self.rng.below(self.size), self.rng.below(self.size));
+            let neighbours = [(x + 1, y), (x, y + 1)];
+            let (nx, ny) = neighbours[self.rng.below(2)];
+            if nx < self.size && ny < self.size {
+                let forward = self.get_wall_index(x, y, nx, ny);
+                let backward = self.get_wall_index(nx, ny, x, y);
+                if !self.walls[forward] {
+                    continue;
+                }
+                self.walls[forward] = false;
+                self.walls[backward] = false;
+                if !self.keeps_layout() {
+                    self.walls[forward] = true;
+                    self.walls[backward] = true;
+                }
+            }
+        }
+
+        let mut placed = 0;
+        for _ in 0..one_way * 8 {
+            if placed == one_way {
+                break;
+            }
+            let (x, y) = (self.rng.below(self.size), self.rng.below(self.size));
+            let open = self.open_neighbours((x, y));
+            let Some(&(nx, ny)) = open.get(self.rng.below(open.len())) else {
+                continue;
+            };
+            // Block the way back, so the passage only runs from (x, y)
+            let backward = self.get_wall_index(nx, ny, x, y);
+            if self.walls[backward] {
+                continue;
+            }
+            self.walls[backward] = true;
+            if self.keeps_layout() {
+                placed += 1;
+            } else {
+                self.walls[backward] = false;
+            }
+        }
+    }
+
+    /// Whether the maze is still solvable without walking round a coloured
+    /// door, on a solution that crosses each collapsing cell only once.
+    fn keeps_layout(&self) -> bool {
+        let Some(solution) = self.solve_from_start() else {
+            return false;
+        };
+        let mut crossed = Vec::new();
+        for &pos in &solution {
+            if self.hazards.is_collapsing(pos) {
+                if crossed.contains(&pos) {
+                    return false;
+                }
+                crossed.push(pos);
+            }
+        }
+        self.objectives
+            .doors
+            .iter()
+            .all(|door| !self.exit_reachable_without(door.position))
+    }
+
+    /// Whether the exit can be reached from the start without stepping on
+    /// `avoid`, ignoring keys.
+    fn exit_reachable_without(&self, avoid: (usize, usize)) -> bool {
+        let mut seen = HashSet::from([self.start_position]);
+        let mut queue = VecDeque::from([self.start_position]);
+        while let Some(pos) = queue.pop_front() {
+            for target in self.open_neighbours(pos) {
+                if target == avoid {
+                    continue;
+                }
+                if target == self.door_position {
+                    return true;
+                }
+                let landing = self.landing(target);
+                if seen.insert(landing) {
+                    queue.push_back(landing);
+                }
+            }
+        }
+        false
+    }
+
+    /// Where the player ends up after stepping onto `pos`.
+    pub(super) fn landing(&self, pos: (usize, usize)) -> (usize, usize) {
+        self.teleporters
+            .iter()
+            .find_map(|&(a, b)| {
+                if a == pos {
+                    Some(b)
+                } else if b == pos {
+                    Some(a)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(pos)
+    }
+
+    /// Pair number (1-based) of the teleporter at `pos`, if any.
+    pub(super) fn teleporter_at(&self, pos: (usize, usize)) -> Option<usize> {
+        self.teleporters
+            .iter()
+            .position(|&(a, b)| a == pos || b == pos)
+            .map(|i| i + 1)
+    }
+}
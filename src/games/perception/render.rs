@@ -30,10 +30,11 @@ impl Perception {
             }
         }
 
-        // Update existing cells
-        for y in 0..self.size {
+        // Update existing cells, which show the player's floor only
+        let first_row = self.current_floor() * self.size;
+        for y in first_row..first_row + self.size {
             for x in 0..self.size {
-                let index = (y * self.size + x) as u32;
+                let index = ((y - first_row) * self.size + x) as u32;
                 if let Some(cell) = maze.children().item(index) {
                     self.update_cell_state(&cell, x, y)?;
                 }
@@ -47,11 +48,13 @@ impl Perception {
         if let Some(completed_el) = self.document.get_element_by_id("moves") {
             completed_el.set_text_content(Some(&self.moves.to_string()));
         }
-        if let Some(timer_el) = self.document.get_element_by_id("timer") {
-            let minutes = self.time_remaining / 60;
-            let seconds = self.time_remaining % 60;
-            timer_el.set_text_content(Some(&format!("{minutes}:{seconds:02}")));
-        }
+        self.render_inventory()?;
+        self.render_mode();
+        self.render_hint_button()?;
+        self.render_floor()?;
+        self.render_ghost()?;
+        self.publish_exports()?;
+        self.update_timer_display();
         Ok(())
     }
 
@@ -72,17 +75,96 @@ impl Perception {
             }
         }
 
+        if self.ghost_position == Some((x, y)) {
+            cell.class_list().add_1("ghost")?;
+        }
+
+        if self.hint == Some((x, y)) {
+            cell.class_list().add_1("hint")?;
+        }
+
+        // Walls the current mode lets the player see
+        for (side, class) in ["wall-top", "wall-right", "wall-bottom", "wall-left"]
+            .iter()
+            .enumerate()
+        {
+            if self.wall_visible(x, y, side) {
+                cell.class_list().add_1(class)?;
+            }
+        }
+
+        // Coloured keys, doors and waypoints
+        let objectives = &self.objectives;
+        let key_colour = objectives.key_at((x, y)).filter(|_| (x, y) != self.current_position);
+        let door_colour = objectives.door_at((x, y)).filter(|_| (x, y) != self.current_position);
+        if let Some(colour) = key_colour.or(door_colour) {
+            cell.class_list().add_1(colour.name())?;
+        }
+        if door_colour.is_some() && objectives.can_enter((x, y)) {
+            cell.class_list().add_1("unlocked")?;
+        }
+        let waypoint = objectives.waypoint_at((x, y));
+        if waypoint.is_some_and(|n| n <= objectives.next_waypoint) {
+            cell.class_list().add_1("waypoint-done")?;
+        }
+
+        // Moving and timed hazards
+        let hazards = &self.hazards;
+        let sentry = hazards.sentry_at((x, y));
+        if sentry {
+            cell.class_list().add_1("sentry")?;
+        }
+        if let Some(closed) = hazards.toggle_at((x, y)) {
+            cell.class_list().add_2("toggle", if closed { "closed" } else { "open" })?;
+        }
+        if hazards.is_collapsing((x, y)) {
+            cell.class_list().add_1(if hazards.is_collapsed((x, y)) { "collapsed" } else { "collapsing" })?;
+        }
+
+        if let Some(pair) = self.teleporter_at((x, y)) {
+            cell.class_list().add_1("teleporter")?;
+            cell.set_attribute("data-pair", &pair.to_string())?;
+        } else {
+            cell.remove_attribute("data-pair")?;
+        }
+
+        let stairs_up = self.stair_target((x, y), true).is_some();
+        let stairs_down = self.stair_target((x, y), false).is_some();
+        cell.class_list().toggle_with_force("stairs-up", stairs_up)?;
+        cell.class_list().toggle_with_force("stairs-down", stairs_down)?;
+
         // Update content
+        let waypoint_label;
         let content = if (x, y) == self.key_position && !self.has_key
             || (x, y) == self.current_position && self.has_key
+            || key_colour.is_some()
         {
             "🔑"
-        } else if (x, y) == self.door_position {
+        } else if (x, y) == self.door_position || door_colour.is_some() {
             "🚪"
         }
         else if (x, y) == self.current_position && !self.has_key {
             "👤"
         }
+        else if sentry {
+            "👾"
+        }
+        else if self.teleporter_at((x, y)).is_some() {
+            "🌀"
+        }
+        else if stairs_up && stairs_down {
+            "↕"
+        }
+        else if stairs_up {
+            "⬆"
+        }
+        else if stairs_down {
+            "⬇"
+        }
+        else if let Some(n) = waypoint {
+            waypoint_label = n.to_string();
+            &waypoint_label
+        }
         else {
             ""
         };
@@ -96,4 +178,31 @@ impl Perception {
 
         Ok(())
     }
+
+    /// Shows the coloured keys held and the waypoint to visit next.
+    pub(super) fn render_inventory(&self) -> Result<(), JsValue> {
+        let Some(inventory) = self.document.get_element_by_id("inventory") else {
+            return Ok(());
+        };
+        while let Some(child) = inventory.first_child() {
+            inventory.remove_child(&child)?;
+        }
+        for colour in &self.objectives.collected {
+            let key = self.document.create_element("span")?;
+            key.set_class_name(colour.name());
+            key.set_text_content(Some("🔑"));
+            inventory.append_child(&key)?;
+        }
+        let waypoints = &self.objectives.waypoints;
+        if !waypoints.is_empty() {
+            let next = self.document.create_element("span")?;
+            next.set_text_content(Some(&if self.objectives.waypoints_done() {
+                " ✓".to_string()
+            } else {
+                format!(" next: {}/{}", self.objectives.next_waypoint + 1, waypoints.len())
+            }));
+            inventory.append_child(&next)?;
+        }
+        Ok(())
+    }
 }
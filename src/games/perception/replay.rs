@@ -0,0 +1,79 @@
+use super::{floors::MAX_FLOORS, get_document, levels, reveal::RevealMode, Perception};
+use crate::games::{
+    recording::{Input, Recording},
+    share::Puzzle,
+};
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen::prelude::*;
+
+impl Perception {
+    /// Appends a player input to the maze's log, after any hazard steps taken
+    /// since the last entry, and refreshes the replay link.
+    pub(super) fn record_input(&mut self, input: Input) {
+        if self.replaying {
+            return;
+        }
+        let ticks_logged = self.log_hazard_ticks();
+        if self.recording.record(input) || ticks_logged {
+            self.recording.publish(&self.document);
+        }
+    }
+
+    /// Drives the game from one input, whether clicked live or replayed.
+    pub(super) fn apply_input(&mut self, input: Input) -> Result<(), JsValue> {
+        match input {
+            Input::Cell(x, y) => {
+                let result = self.try_move(x, y);
+                if result != 0 {
+                    self.render()?;
+                }
+            }
+            Input::Timeout => self.reset_on_timeout(js_sys::Date::now() / 1000.0),
+            Input::Reset => self.reset_to_level_one()?,
+            Input::Tick(steps) => self.hazard_tick(steps)?,
+            Input::Hint => self.take_hint()?,
+            Input::Mode(code, seconds) => self.set_mode(RevealMode::from_code(code, seconds))?,
+            Input::Up | Input::Down => {
+                let result = self.try_climb(input == Input::Up);
+                if result != 0 {
+                    self.render()?;
+                }
+            }
+            Input::Floors(floors) => self.set_floors(floors),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Starts a session on a maze someone shared, from its seed and level.
+    pub(crate) fn play_puzzle(puzzle: &Puzzle) -> Result<(), JsValue> {
+        let document = get_document();
+        let floors = puzzle.variant.clamp(1, MAX_FLOORS);
+        let stage = levels::stage(puzzle.level);
+        let mut game = Self::generate(stage, floors, puzzle.seed, document.clone());
+        game.level = puzzle.level;
+        game.restart_timer();
+        game.recording = Recording::new('p', puzzle.seed, puzzle.level).variant(floors);
+        puzzle.announce(&document);
+        game.render()?;
+        game.start()
+    }
+
+    /// Rebuilds the recorded session's first maze and re-drives it from the log.
+    pub(crate) fn replay(recording: &Recording) -> Result<(), JsValue> {
+        let floors = recording.variant.clamp(1, MAX_FLOORS);
+        let stage = levels::stage(recording.level);
+        let mut game = Self::generate(stage, floors, recording.seed, get_document());
+        game.level = recording.level;
+        game.replaying = true;
+        game.render()?;
+
+        let game_state = Rc::new(RefCell::new(game));
+        Self::setup_review_handlers(&game_state)?;
+        recording.play(move |input| {
+            if let Err(e) = game_state.borrow_mut().apply_input(input) {
+                web_sys::console::log_2(&"Failed to replay input:".into(), &e);
+            }
+        })
+    }
+}
@@ -0,0 +1,196 @@
+use super::{levels, Perception};
+use crate::games::{clock, recording::Input};
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen::prelude::*;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+
+/// Longest preview the settings accept, in seconds.
+const MAX_PREVIEW_SECONDS: usize = 60;
+
+/// Which walls the player gets to see.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) enum RevealMode {
+    /// Walls are only found by walking into them.
+    #[default]
+    Hidden,
+    /// Every wall is drawn; completing a maze does not advance the level.
+    Practice,
+    /// Walls stay drawn once they have been hit.
+    RevealOnHit,
+    /// The whole maze is shown for this many seconds, then hidden.
+    Preview(usize),
+}
+
+impl RevealMode {
+    /// Value of the matching `#reveal-mode` option, `None` being the
+    /// level's own rule.
+    fn name(mode: Option<Self>) -> &'static str {
+        match mode {
+            None => "default",
+            Some(RevealMode::Hidden) => "hidden",
+            Some(RevealMode::Practice) => "practice",
+            Some(RevealMode::RevealOnHit) => "reveal",
+            Some(RevealMode::Preview(_)) => "preview",
+        }
+    }
+
+    fn from_name(name: &str, seconds: usize) -> Option<Self> {
+        match name {
+            "hidden" => Some(RevealMode::Hidden),
+            "practice" => Some(RevealMode::Practice),
+            "reveal" => Some(RevealMode::RevealOnHit),
+            "preview" => Some(RevealMode::Preview(seconds.clamp(1, MAX_PREVIEW_SECONDS))),
+            _ => None,
+        }
+    }
+
+    /// Compact form for the session log, as `(code, preview seconds)`. Code
+    /// 0 is the level's own rule, which is what older logs meant by hidden.
+    fn to_input(mode: Option<Self>) -> Input {
+        match mode {
+            None => Input::Mode(0, 0),
+            Some(RevealMode::Practice) => Input::Mode(1, 0),
+            Some(RevealMode::RevealOnHit) => Input::Mode(2, 0),
+            Some(RevealMode::Preview(seconds)) => Input::Mode(3, seconds),
+            Some(RevealMode::Hidden) => Input::Mode(4, 0),
+        }
+    }
+
+    pub(super) fn from_code(code: usize, seconds: usize) -> Option<Self> {
+        match code {
+            1 => Some(RevealMode::Practice),
+            2 => Some(RevealMode::RevealOnHit),
+            3 => Some(RevealMode::Preview(seconds.clamp(1, MAX_PREVIEW_SECONDS))),
+            4 => Some(RevealMode::Hidden),
+            _ => None,
+        }
+    }
+
+    /// Whether completing a maze in this mode moves on to the next level.
+    pub(super) fn advances_level(self) -> bool {
+        self != RevealMode::Practice
+    }
+}
+
+/// A completed maze and the mode it was played in.
+#[derive(Clone, Serialize, Deserialize)]
+pub(super) struct Completion {
+    pub(super) level: usize,
+    pub(super) mode: RevealMode,
+    pub(super) moves: usize,
+    /// Difficulty score of the maze, see `Metrics::score`.
+    #[serde(default)]
+    pub(super) difficulty: f64,
+}
+
+impl Perception {
+    /// The player's chosen mode, or the level's own reveal rule if they
+    /// haven't chosen one.
+    pub(super) fn reveal_mode(&self) -> RevealMode {
+        self.mode.unwrap_or(levels::stage(self.level).reveal)
+    }
+
+    /// Whether the wall leaving `(x, y)` through `side` should be drawn.
+    pub(super) fn wall_visible(&self, x: usize, y: usize, side: usize) -> bool {
+        let index = (y * self.size + x) * 4 + side;
+        if !self.walls[index] {
+            return false;
+        }
+        match self.reveal_mode() {
+            RevealMode::Hidden => false,
+            RevealMode::Practice => true,
+            RevealMode::RevealOnHit => self.discovered.contains(&index),
+            RevealMode::Preview(_) => clock::now() < self.preview_until,
+        }
+    }
+
+    /// Remembers a wall the player walked into, for reveal-on-hit.
+    pub(super) fn discover_wall(&mut self, index: usize) {
+        if !self.discovered.contains(&index) {
+            self.discovered.push(index);
+        }
+    }
+
+    /// Starts showing a fresh maze according to the current mode.
+    pub(super) fn begin_reveal(&mut self) {
+        self.discovered.clear();
+        self.preview_until = match self.reveal_mode() {
+            RevealMode::Preview(seconds) => clock::now() + seconds as f64 * 1000.0,
+            _ => 0.0,
+        };
+    }
+
+    /// Hides the maze again once its preview has run out.
+    pub(super) fn check_preview(&mut self) -> Result<(), JsValue> {
+        if self.preview_until > 0.0 && clock::now() >= self.preview_until {
+            self.preview_until = 0.0;
+            self.render()?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn set_mode(&mut self, mode: Option<RevealMode>) -> Result<(), JsValue> {
+        self.mode = mode;
+        self.begin_reveal();
+        self.save_state()?;
+        self.render()
+    }
+
+    /// Keeps the mode controls in step with the game, e.g. after a reload.
+    pub(super) fn render_mode(&self) {
+        if let Some(select) = self.document.get_element_by_id("reveal-mode") {
+            if let Ok(select) = select.dyn_into::<HtmlSelectElement>() {
+                select.set_value(RevealMode::name(self.mode));
+            }
+        }
+        if let Some(RevealMode::Preview(seconds)) = self.mode {
+            if let Some(input) = self.document.get_element_by_id("preview-seconds") {
+                if let Ok(input) = input.dyn_into::<HtmlInputElement>() {
+                    input.set_value(&seconds.to_string());
+                }
+            }
+        }
+    }
+
+    pub(super) fn setup_mode_handlers(game_state: &Rc<RefCell<Self>>) -> Result<(), JsValue> {
+        let document = game_state.borrow().document.clone();
+        let (Some(select), Some(seconds)) = (
+            document.get_element_by_id("reveal-mode"),
+            document.get_element_by_id("preview-seconds"),
+        ) else {
+            return Ok(());
+        };
+        let select: HtmlSelectElement = select.dyn_into()?;
+        let seconds: HtmlInputElement = seconds.dyn_into()?;
+
+        let handler = {
+            let game_state = game_state.clone();
+            let (select, seconds) = (select.clone(), seconds.clone());
+            Closure::wrap(Box::new(move |_event: web_sys::Event| {
+                if let Ok(mut game) = game_state.try_borrow_mut() {
+                    // The maze is hidden behind the pause overlay, so a new
+                    // mode would start there unseen; put the controls back
+                    if !clock::is_running() {
+                        game.render_mode();
+                        return;
+                    }
+                    let preview = seconds.value().parse().unwrap_or(5);
+                    let mode = RevealMode::from_name(&select.value(), preview);
+                    if mode == game.mode {
+                        return;
+                    }
+                    let input = RevealMode::to_input(mode);
+                    game.record_input(input);
+                    game.apply_input(input).unwrap_or_else(|e| {
+                        web_sys::console::log_2(&"Failed to change mode:".into(), &e);
+                    });
+                }
+            }) as Box<dyn FnMut(_)>)
+        };
+        select.add_event_listener_with_callback("change", handler.as_ref().unchecked_ref())?;
+        seconds.add_event_listener_with_callback("change", handler.as_ref().unchecked_ref())?;
+        handler.forget();
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+use super::Perception;
+use serde::{Deserialize, Serialize};
+use std::{cell::Cell, cell::RefCell, rc::Rc};
+use wasm_bindgen::prelude::*;
+use web_sys::Element;
+
+/// Milliseconds between replay steps.
+const REPLAY_STEP_MS: i32 = 250;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(super) struct WallHit {
+    pub(super) from: (usize, usize),
+    pub(super) to: (usize, usize),
+}
+
+/// One run from the start cell, ending either on a wall or at the door.
+#[derive(Clone, Serialize, Deserialize)]
+pub(super) struct Attempt {
+    pub(super) path: Vec<(usize, usize)>,
+    pub(super) hit: Option<WallHit>,
+    /// Hints taken during the attempt, each costing `HINT_PENALTY` moves.
+    #[serde(default)]
+    pub(super) hints: usize,
+}
+
+/// Snapshot of a completed maze, kept so it can be reviewed after level-up.
+#[derive(Clone, Serialize, Deserialize)]
+pub(super) struct Review {
+    size: usize,
+    /// Floors of the maze, drawn one below the other.
+    #[serde(default = "super::floors::default_floors")]
+    floors: usize,
+    level: usize,
+    start: (usize, usize),
+    key: (usize, usize),
+    door: (usize, usize),
+    attempts: Vec<Attempt>,
+    /// Interval stepping through the attempts, while a replay runs. Shared
+    /// by every copy of the review, so any of them can stop it.
+    #[serde(skip)]
+    replay: Rc<Cell<Option<i32>>>,
+}
+
+impl Review {
+    /// Number of wall hits made from each cell, indexed `y * size + x`.
+    fn heatmap(&self) -> Vec<usize> {
+        let mut heat = vec![0; self.size * self.size * self.floors.max(1)];
+        for hit in self.attempts.iter().filter_map(|a| a.hit.as_ref()) {
+            heat[hit.from.1 * self.size + hit.from.0] += 1;
+        }
+        heat
+    }
+
+    /// Stops the replay of the attempts, if one is running.
+    fn stop_replay(&self) {
+        if let (Some(handle), Some(window)) = (self.replay.take(), web_sys::window()) {
+            window.clear_interval_with_handle(handle);
+        }
+    }
+
+    fn label(&self, (x, y): (usize, usize)) -> &'static str {
+        if (x, y) == self.key {
+            "🔑"
+        } else if (x, y) == self.door {
+            "🚪"
+        } else if (x, y) == self.start {
+            "👤"
+        } else {
+            ""
+        }
+    }
+}
+
+impl Perception {
+    /// Moves the current path into the attempt history.
+    pub(super) fn end_attempt(&mut self, hit: Option<WallHit>) {
+        let path = std::mem::take(&mut self.path);
+        let hints = std::mem::take(&mut self.hints_used);
+        self.attempts.push(Attempt { path, hit, hints });
+    }
+
+    /// Captures the attempts on the maze just completed for the review overlay.
+    pub(super) fn capture_review(&mut self) {
+        self.end_attempt(None);
+        if let Some(review) = &self.review {
+            review.stop_replay();
+        }
+        self.review = Some(Review {
+            size: self.size,
+            floors: self.floors,
+            level: self.level,
+            start: self.start_position,
+            key: self.key_position,
+            door: self.door_position,
+            attempts: std::mem::take(&mut self.attempts),
+            replay: Rc::default(),
+        });
+    }
+
+    pub(super) fn render_review(&self) -> Result<(), JsValue> {
+        let (Some(review), Some(panel), Some(grid)) = (
+            self.review.as_ref(),
+            self.document.get_element_by_id("review"),
+            self.document.get_element_by_id("review-grid"),
+        ) else {
+            return Ok(());
+        };
+
+        grid.set_attribute(
+            "style",
+            &format!(
+                "grid-template-columns: repeat({}, var(--cell-size))",
+                review.size
+            ),
+        )?;
+        while let Some(child) = grid.first_child() {
+            grid.remove_child(&child)?;
+        }
+
+        let heat = review.heatmap();
+        let hottest = heat.iter().copied().max().unwrap_or(0).max(1);
+        for (index, &hits) in heat.iter().enumerate() {
+            let cell = self.document.create_element("div")?;
+            cell.set_class_name("cell");
+            cell.set_attribute(
+                "style",
+                &format!("--heat: {:.2}", hits as f64 / hottest as f64),
+            )?;
+            let label = review.label((index % review.size, index / review.size));
+            cell.set_attribute("data-hits", &hits.to_string())?;
+            cell.set_text_content(Some(label));
+            grid.append_child(&cell)?;
+        }
+
+        let hits = review.attempts.iter().filter(|a| a.hit.is_some()).count();
+        let hints_taken: usize = review.attempts.iter().map(|a| a.hints).sum();
+        if let Some(caption) = self.document.get_element_by_id("review-caption") {
+            caption.set_text_content(Some(&format!(
+                "Level {}: {} wall {} in {} {}{}",
+                review.level,
+                hits,
+                if hits == 1 { "hit" } else { "hits" },
+                review.attempts.len(),
+                if review.attempts.len() == 1 {
+                    "attempt"
+                } else {
+                    "attempts"
+                },
+                match hints_taken {
+                    0 => String::new(),
+                    1 => ", 1 hint".to_string(),
+                    n => format!(", {n} hints"),
+                },
+            )));
+        }
+        panel.remove_attribute("hidden")?;
+        Ok(())
+    }
+
+    pub(super) fn setup_review_handlers(game_state: &Rc<RefCell<Self>>) -> Result<(), JsValue> {
+        let document = game_state.borrow().document.clone();
+
+        if let Some(close_btn) = document.get_element_by_id("review-close") {
+            let document = document.clone();
+            let game_state = game_state.clone();
+            let handler = Closure::wrap(Box::new(move |_event: web_sys::MouseEvent| {
+                if let Some(review) = &game_state.borrow().review {
+                    review.stop_replay();
+                }
+                if let Some(panel) = document.get_element_by_id("review") {
+                    let _ = panel.set_attribute("hidden", "");
+                }
+            }) as Box<dyn FnMut(_)>);
+            close_btn
+                .add_event_listener_with_callback("click", handler.as_ref().unchecked_ref())?;
+            handler.forget();
+        }
+
+        if let Some(replay_btn) = document.get_element_by_id("review-replay") {
+            let game_state = game_state.clone();
+            let handler = Closure::wrap(Box::new(move |_event: web_sys::MouseEvent| {
+                let review = game_state.borrow().review.clone();
+                if let Some(review) = review {
+                    let document = game_state.borrow().document.clone();
+                    Self::replay_attempts(&document, review).unwrap_or_else(|e| {
+                        web_sys::console::log_2(&"Failed to replay attempts:".into(), &e);
+                    });
+                }
+            }) as Box<dyn FnMut(_)>);
+            replay_btn
+                .add_event_listener_with_callback("click", handler.as_ref().unchecked_ref())?;
+            handler.forget();
+        }
+
+        Ok(())
+    }
+
+    /// Steps through every attempt of the reviewed maze, one cell per tick,
+    /// stopping any replay already running first.
+    fn replay_attempts(document: &web_sys::Document, review: Review) -> Result<(), JsValue> {
+        review.stop_replay();
+        let Some(grid) = document.get_element_by_id("review-grid") else {
+            return Ok(());
+        };
+        let caption = document.get_element_by_id("review-caption");
+        let window = web_sys::window().expect("no global window exists");
+
+        let handle = review.replay.clone();
+        let (mut attempt, mut step) = (0, 0);
+        let callback = Closure::wrap(Box::new(move || {
+            let Some(current) = review.attempts.get(attempt) else {
+                review.stop_replay();
+                return;
+            };
+            let cell_at =
+                |(x, y): (usize, usize)| grid.children().item((y * review.size + x) as u32);
+
+            if step == 0 {
+                for i in 0..grid.children().length() {
+                    if let Some(cell) = grid.children().item(i) {
+                        clear_replay_classes(&cell);
+                    }
+                }
+                if let Some(caption) = &caption {
+                    caption.set_text_content(Some(&format!(
+                        "Attempt {} of {}",
+                        attempt + 1,
+                        review.attempts.len()
+                    )));
+                }
+            }
+
+            if let Some(&pos) = current.path.get(step) {
+                if let Some(prev) = step.checked_sub(1).and_then(|s| current.path.get(s)) {
+                    if let Some(cell) = cell_at(*prev) {
+                        let _ = cell.class_list().remove_1("replay-head");
+                    }
+                }
+                if let Some(cell) = cell_at(pos) {
+                    let _ = cell.class_list().add_2("replay-path", "replay-head");
+                }
+                step += 1;
+            } else {
+                if let Some(hit) = &current.hit {
+                    if let Some(cell) = cell_at(hit.to) {
+                        let _ = cell.class_list().add_1("replay-hit");
+                    }
+                }
+                attempt += 1;
+                step = 0;
+            }
+        }) as Box<dyn FnMut()>);
+
+        handle.set(Some(
+            window.set_interval_with_callback_and_timeout_and_arguments_0(
+                callback.as_ref().unchecked_ref(),
+                REPLAY_STEP_MS,
+            )?,
+        ));
+        callback.forget();
+        Ok(())
+    }
+}
+
+fn clear_replay_classes(cell: &Element) {
+    let _ = cell
+        .class_list()
+        .remove_3("replay-path", "replay-head", "replay-hit");
+}
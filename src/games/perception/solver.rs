@@ -0,0 +1,127 @@
+use super::Perception;
+use std::collections::{HashMap, VecDeque};
+
+/// Everything that decides which moves are possible next.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct SolverState {
+    pos: (usize, usize),
+    has_key: bool,
+    /// Bit per collected coloured key, by position in `objectives.keys`.
+    keys: u8,
+    waypoint: usize,
+}
+
+impl Perception {
+    /// Neighbours reachable in one step from `pos`, honouring one-way walls
+    /// and including stairs to other floors.
+    pub(super) fn open_neighbours(&self, (x, y): (usize, usize)) -> Vec<(usize, usize)> {
+        let mut out = Vec::new();
+        if y > 0 {
+            out.push((x, y - 1));
+        }
+        if x + 1 < self.size {
+            out.push((x + 1, y));
+        }
+        if y + 1 < self.rows() {
+            out.push((x, y + 1));
+        }
+        if x > 0 {
+            out.push((x - 1, y));
+        }
+        out.retain(|&(nx, ny)| !self.walls[self.get_wall_index(x, y, nx, ny)]);
+        out.extend([true, false].iter().filter_map(|&up| self.stair_target((x, y), up)));
+        out
+    }
+
+    fn current_solver_state(&self) -> SolverState {
+        let keys = self
+            .objectives
+            .keys
+            .iter()
+            .enumerate()
+            .filter(|(_, key)| self.objectives.collected.contains(&key.colour))
+            .fold(0, |mask, (i, _)| mask | 1 << i);
+        SolverState {
+            pos: self.current_position,
+            has_key: self.has_key,
+            keys,
+            waypoint: self.objectives.next_waypoint,
+        }
+    }
+
+    /// Shortest list of cells to click to reach the exit from the current
+    /// position and progress, or `None` if the exit is out of reach.
+    /// Hazards are time-dependent and ignored.
+    pub(super) fn solve(&self) -> Option<Vec<(usize, usize)>> {
+        self.solve_from(self.current_solver_state())
+    }
+
+    /// Shortest list of cells to click to reach the exit on a fresh attempt
+    /// from the start cell, or `None` if the exit is out of reach.
+    /// Hazards are time-dependent and ignored.
+    pub(super) fn solve_from_start(&self) -> Option<Vec<(usize, usize)>> {
+        let mut state = SolverState {
+            pos: self.start_position,
+            has_key: false,
+            keys: 0,
+            waypoint: 0,
+        };
+        self.advance(&mut state, self.start_position);
+        self.solve_from(state)
+    }
+
+    fn solve_from(&self, start: SolverState) -> Option<Vec<(usize, usize)>> {
+        let mut parent: HashMap<SolverState, (SolverState, (usize, usize))> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        let mut seen = std::collections::HashSet::from([start]);
+
+        while let Some(state) = queue.pop_front() {
+            for target in self.open_neighbours(state.pos) {
+                if target == self.door_position {
+                    if state.has_key && state.waypoint >= self.objectives.waypoints.len() {
+                        let mut clicks = vec![target];
+                        let mut current = state;
+                        while let Some(&(prev, click)) = parent.get(&current) {
+                            clicks.push(click);
+                            current = prev;
+                        }
+                        clicks.reverse();
+                        return Some(clicks);
+                    }
+                    continue;
+                }
+                let locked = self.objectives.keys.iter().enumerate().any(|(i, key)| {
+                    self.objectives.door_at(target) == Some(key.colour) && state.keys & 1 << i == 0
+                });
+                if locked {
+                    continue;
+                }
+
+                let mut next = state;
+                self.advance(&mut next, target);
+                if seen.insert(next) {
+                    parent.insert(next, (state, target));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Applies stepping onto `target`: teleporting, then picking things up.
+    fn advance(&self, state: &mut SolverState, target: (usize, usize)) {
+        let landing = self.landing(target);
+        state.pos = landing;
+        if landing == self.key_position {
+            state.has_key = true;
+        }
+        for (i, key) in self.objectives.keys.iter().enumerate() {
+            if key.position == landing {
+                state.keys |= 1 << i;
+            }
+        }
+        if self.objectives.waypoints.get(state.waypoint) == Some(&landing) {
+            state.waypoint += 1;
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use super::Perception;
+use super::{levels, Perception};
+use crate::games::{clock, recording::Input, settings};
 use std::{cell::RefCell, rc::Rc};
 use wasm_bindgen::prelude::*;
 use web_sys::console;
@@ -10,6 +11,10 @@ impl Perception {
             Closure::wrap(Box::new(move || {
                 if let Ok(mut game) = game_state.try_borrow_mut() {
                     let now = js_sys::Date::now() / 1000.0;
+                    if !clock::is_running() {
+                        game.last_tick = now;
+                        return;
+                    }
                     let delta = (now - game.last_tick) as i32;
                     if delta >= 1 {
                         game.update_timer(now);
@@ -36,34 +41,66 @@ impl Perception {
     }
 
     fn update_timer(&mut self, now: f64) {
-        self.time_remaining -= 1;
         self.last_tick = now;
-
-        if self.time_remaining <= 0 {
-            self.reset_on_timeout(now);
+        if !self.untimed {
+            self.time_remaining -= 1;
+            if self.time_remaining <= 0 {
+                self.record_input(Input::Timeout);
+                self.reset_on_timeout(now);
+            }
         }
 
         self.update_timer_display();
+        self.check_preview().unwrap_or_else(|_| {
+            console::log_1(&"Failed to hide maze preview".into());
+        });
         self.save_state().unwrap_or_else(|_| {
             console::log_1(&"Failed to save game state".into());
         });
     }
 
-    fn update_timer_display(&self) {
+    pub(super) fn update_timer_display(&self) {
         if let Some(timer_el) = self.document.get_element_by_id("timer") {
+            if self.untimed {
+                timer_el.set_text_content(Some("Untimed"));
+                return;
+            }
             let minutes = self.time_remaining / 60;
             let seconds = self.time_remaining % 60;
             timer_el.set_text_content(Some(&format!("{minutes}:{seconds:02}")));
         }
     }
 
-    fn reset_on_timeout(&mut self, now: f64) {
-        let new_game = Self::create_maze(self.size, self.document.clone());
-        self.walls = new_game.walls;
-        self.key_position = new_game.key_position;
-        self.door_position = new_game.door_position;
+    /// Seconds allowed on a maze at the current level: the schedule's limit
+    /// adjusted by the player's settings, or `None` when untimed.
+    fn time_limit(&self) -> Option<i32> {
+        let seconds = levels::stage(self.level).seconds;
+        settings::time_limit('p', seconds, self.level).map(|limit| limit as i32)
+    }
+
+    /// Starts the clock on a fresh maze.
+    pub(super) fn restart_timer(&mut self) {
+        let limit = self.time_limit();
+        self.untimed = limit.is_none();
+        self.time_remaining = limit.unwrap_or(0);
+        self.last_tick = js_sys::Date::now() / 1000.0;
+    }
+
+    /// Seconds spent on the maze so far; untimed mazes count the current
+    /// attempt only.
+    pub(super) fn elapsed_seconds(&self) -> u32 {
+        if self.untimed {
+            return ((clock::now() - self.run_started) / 1000.0) as u32;
+        }
+        self.time_limit()
+            .map_or(0, |limit| (limit - self.time_remaining).max(0) as u32)
+    }
+
+    pub(super) fn reset_on_timeout(&mut self, now: f64) {
+        self.next_maze();
         self.reset_position();
-        self.time_remaining = 300;
+        self.attempts.clear();
+        self.restart_timer();
         self.last_tick = now;
         self.render().unwrap();
     }
@@ -0,0 +1,373 @@
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, convert::TryFrom, fmt::Write, rc::Rc};
+use wasm_bindgen::prelude::*;
+use web_sys::Document;
+
+/// Longest pause kept between two events; longer gaps are shortened on replay.
+const MAX_GAP_MS: u32 = 5_000;
+/// Most events a log holds. Later inputs are left out, so the replay link
+/// stays a usable length and replays the session up to that point.
+const MAX_EVENTS: usize = 1_000;
+
+/// A single player input, in the form each game's state machine consumes it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Input {
+    /// Perception: click on the maze cell at `(x, y)`.
+    Cell(usize, usize),
+    /// Numeracy: tap on the bubble at this index.
+    Bubble(usize),
+    /// Mental Rotation: rotate the tile at this index.
+    Rotate(usize),
+    /// Mental Rotation: reverse the tile at this index.
+    Reverse(usize),
+    /// The round or level timer ran out.
+    Timeout,
+    /// Perception: moving hazards advanced this many steps since the last
+    /// event.
+    Tick(usize),
+    /// Perception: the player asked for a hint.
+    Hint,
+    /// Perception: the wall-reveal mode changed, as its code and preview
+    /// length in seconds.
+    Mode(usize, usize),
+    /// Perception: the player took the stairs up.
+    Up,
+    /// Perception: the player took the stairs down.
+    Down,
+    /// Perception: mazes now have this many floors.
+    Floors(usize),
+    /// Mental Rotation: take back the last tile move.
+    Undo,
+    /// Mental Rotation: make the last move taken back again.
+    Redo,
+    /// Numeracy: a round ended and the next is played at this level. How
+    /// far a round moves the level depends on how long it took, so replays
+    /// take it from the log rather than the clock.
+    Level(usize),
+    /// The game's reset action (reset button, or a resumed Numeracy round).
+    Reset,
+}
+
+/// Timestamped log of a session's inputs plus what is needed to regenerate
+/// the starting state: the game, its seed, its level and its layout.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Recording {
+    pub game: char,
+    pub seed: u32,
+    pub level: usize,
+    /// Game-specific layout option, as in share codes: Perception's floor
+    /// count, 1 for the standard layout.
+    #[serde(default)]
+    pub variant: usize,
+    #[serde(default)]
+    last_event: f64,
+    /// Milliseconds since the previous event, and the event itself.
+    events: Vec<(u32, Input)>,
+}
+
+impl Recording {
+    pub fn new(game: char, seed: u32, level: usize) -> Self {
+        Self {
+            game,
+            seed,
+            level,
+            variant: 1,
+            last_event: js_sys::Date::now(),
+            events: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn variant(mut self, variant: usize) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    /// Logs `input`, unless the log is full. Returns whether it was logged.
+    pub fn record(&mut self, input: Input) -> bool {
+        if self.events.len() >= MAX_EVENTS {
+            return false;
+        }
+        let now = js_sys::Date::now();
+        let gap = (now - self.last_event).clamp(0.0, f64::from(MAX_GAP_MS)) as u32;
+        self.last_event = now;
+        self.events.push((gap, input));
+        true
+    }
+
+    /// Compact URL-safe form: `<game><seed>.<level>`, with `-<variant>` for
+    /// anything but the standard layout, followed by one
+    /// `.<gap><OP><args>` section per event, numbers in lowercase base 36 and
+    /// the op as a single uppercase letter.
+    pub fn encode(&self) -> String {
+        let mut out = format!(
+            "{}{}.{}",
+            self.game,
+            base36(self.seed as usize),
+            base36(self.level)
+        );
+        if self.variant > 1 {
+            out.push('-');
+            out.push_str(&base36(self.variant));
+        }
+        for &(gap, input) in &self.events {
+            out.push('.');
+            out.push_str(&base36(gap as usize));
+            let _ = match input {
+                Input::Cell(x, y) => write!(out, "C{}-{}", base36(x), base36(y)),
+                Input::Bubble(i) => write!(out, "B{}", base36(i)),
+                Input::Rotate(i) => write!(out, "R{}", base36(i)),
+                Input::Reverse(i) => write!(out, "F{}", base36(i)),
+                Input::Timeout => write!(out, "T"),
+                Input::Tick(steps) => write!(out, "H{}", base36(steps)),
+                Input::Hint => write!(out, "N"),
+                Input::Mode(mode, seconds) => write!(out, "M{}-{}", base36(mode), base36(seconds)),
+                Input::Up => write!(out, "U"),
+                Input::Down => write!(out, "D"),
+                Input::Floors(floors) => write!(out, "S{}", base36(floors)),
+                Input::Undo => write!(out, "Z"),
+                Input::Redo => write!(out, "Y"),
+                Input::Level(level) => write!(out, "L{}", base36(level)),
+                Input::Reset => write!(out, "X"),
+            };
+        }
+        out
+    }
+
+    pub fn decode(code: &str) -> Option<Self> {
+        let mut sections = code.split('.');
+        let header = sections.next()?;
+        let game = header.chars().next()?;
+        let seed = u32::try_from(from_base36(&header[game.len_utf8()..])?).ok()?;
+        let level_section = sections.next()?;
+        let (level, variant) = match level_section.split_once('-') {
+            Some((level, variant)) => (level, from_base36(variant)?),
+            None => (level_section, 1),
+        };
+        let level = from_base36(level)?;
+
+        let mut events = Vec::new();
+        for section in sections {
+            // Numbers are lowercase base 36, so the op is the only uppercase letter.
+            let op_at = section.find(|c: char| c.is_ascii_uppercase())?;
+            let gap = u32::try_from(from_base36(&section[..op_at])?).ok()?;
+            let args = &section[op_at + 1..];
+            let input = match section.as_bytes()[op_at] {
+                b'C' => {
+                    let (x, y) = args.split_once('-')?;
+                    Input::Cell(from_base36(x)?, from_base36(y)?)
+                }
+                b'B' => Input::Bubble(from_base36(args)?),
+                b'R' => Input::Rotate(from_base36(args)?),
+                b'F' => Input::Reverse(from_base36(args)?),
+                b'T' => Input::Timeout,
+                // Older links logged one step per event, without a count
+                b'H' if args.is_empty() => Input::Tick(1),
+                b'H' => Input::Tick(from_base36(args)?),
+                b'N' => Input::Hint,
+                b'M' => {
+                    let (mode, seconds) = args.split_once('-')?;
+                    Input::Mode(from_base36(mode)?, from_base36(seconds)?)
+                }
+                b'U' => Input::Up,
+                b'D' => Input::Down,
+                b'S' => Input::Floors(from_base36(args)?),
+                b'Z' => Input::Undo,
+                b'Y' => Input::Redo,
+                b'L' => Input::Level(from_base36(args)?),
+                b'X' => Input::Reset,
+                _ => return None,
+            };
+            events.push((gap.min(MAX_GAP_MS), input));
+        }
+
+        Some(Self {
+            game,
+            seed,
+            level,
+            variant,
+            last_event: 0.0,
+            events,
+        })
+    }
+
+    /// Points the page's `#replay-link` at a replay of this recording.
+    pub fn publish(&self, document: &Document) {
+        if let Some(link) = document.get_element_by_id("replay-link") {
+            let _ = link.set_attribute("href", &format!("?replay={}", self.encode()));
+        }
+    }
+
+    /// Feeds every event to `apply` with its original spacing.
+    pub fn play(&self, apply: impl FnMut(Input) + 'static) -> Result<(), JsValue> {
+        if let Some(banner) = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id("replay-banner"))
+        {
+            banner.remove_attribute("hidden")?;
+        }
+        schedule(Rc::new(RefCell::new(apply)), self.events.clone(), 0)
+    }
+}
+
+fn schedule(
+    apply: Rc<RefCell<dyn FnMut(Input)>>,
+    events: Vec<(u32, Input)>,
+    next: usize,
+) -> Result<(), JsValue> {
+    let Some(&(gap, _)) = events.get(next) else {
+        return Ok(());
+    };
+    let window = web_sys::window().expect("no global window exists");
+    let callback = Closure::once(move || {
+        let (_, input) = events[next];
+        (apply.borrow_mut())(input);
+        if let Err(e) = schedule(apply, events, next + 1) {
+            web_sys::console::log_2(&"Failed to schedule replay event:".into(), &e);
+        }
+    });
+    window.set_timeout_with_callback_and_timeout_and_arguments_0(
+        callback.as_ref().unchecked_ref(),
+        gap as i32,
+    )?;
+    callback.forget();
+    Ok(())
+}
+
+/// Reads a parameter from the page's query string.
+pub fn query_param(name: &str) -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    web_sys::UrlSearchParams::new_with_str(&search)
+        .ok()?
+        .get(name)
+}
+
+pub fn base36(mut n: usize) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let mut out = Vec::new();
+    loop {
+        out.push(DIGITS[n % 36]);
+        n /= 36;
+        if n == 0 {
+            break;
+        }
+    }
+    out.reverse();
+    String::from_utf8(out).expect("base 36 digits are ASCII")
+}
+
+pub fn from_base36(s: &str) -> Option<usize> {
+    if s.is_empty() {
+        return None;
+    }
+    usize::from_str_radix(s, 36).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording(events: Vec<(u32, Input)>) -> Recording {
+        Recording {
+            game: 'p',
+            seed: 123_456,
+            level: 7,
+            variant: 1,
+            last_event: 0.0,
+            events,
+        }
+    }
+
+    #[test]
+    fn every_input_round_trips() {
+        let inputs = [
+            Input::Cell(3, 35),
+            Input::Bubble(2),
+            Input::Rotate(11),
+            Input::Reverse(0),
+            Input::Timeout,
+            Input::Tick(40),
+            Input::Hint,
+            Input::Mode(3, 12),
+            Input::Up,
+            Input::Down,
+            Input::Floors(2),
+            Input::Undo,
+            Input::Redo,
+            Input::Level(4),
+            Input::Reset,
+        ];
+        let original = recording(
+            inputs
+                .iter()
+                .enumerate()
+                .map(|(i, &input)| (i as u32 * 97, input))
+                .collect(),
+        );
+        let decoded = Recording::decode(&original.encode()).unwrap();
+        assert_eq!(decoded.game, 'p');
+        assert_eq!(decoded.seed, 123_456);
+        assert_eq!(decoded.level, 7);
+        assert_eq!(decoded.variant, 1);
+        assert_eq!(decoded.events, original.events);
+    }
+
+    #[test]
+    fn encodes_compactly() {
+        let code = recording(vec![(0, Input::Cell(1, 2)), (5_000, Input::Tick(36))]).encode();
+        assert_eq!(code, "p2n9c.7.0C1-2.3uwH10");
+    }
+
+    #[test]
+    fn variant_goes_in_the_header() {
+        let code = Recording::decode("p2n9c.7").unwrap().variant(3).encode();
+        assert_eq!(code, "p2n9c.7-3");
+        assert_eq!(Recording::decode(&code).unwrap().variant, 3);
+    }
+
+    #[test]
+    fn older_codes_still_decode() {
+        let decoded = Recording::decode("p2n9c.7.aH.0H").unwrap();
+        assert_eq!(
+            decoded.events,
+            vec![(10, Input::Tick(1)), (0, Input::Tick(1))]
+        );
+    }
+
+    #[test]
+    fn long_gaps_are_shortened() {
+        let decoded = Recording::decode("n1.1.zzzzB0").unwrap();
+        assert_eq!(decoded.events, vec![(MAX_GAP_MS, Input::Bubble(0))]);
+    }
+
+    #[test]
+    fn malformed_codes_are_rejected() {
+        for code in [
+            "",
+            "p",
+            "p1",
+            "p1.",
+            "pzzzzzzzzz.1",
+            "p1.1.Q",
+            "p1.1.0Q1",
+            "p1.1.0C1",
+            "p1.1.0C-1",
+            "p1.1.0B",
+            "p1.1.0M1",
+            "p1.1-.0T",
+            "p1.1.!T",
+        ] {
+            assert!(Recording::decode(code).is_none(), "{:?} decoded", code);
+        }
+    }
+
+    #[test]
+    fn base36_round_trips() {
+        for n in [0, 1, 35, 36, 1_295, 1_296, u32::MAX as usize] {
+            assert_eq!(from_base36(&base36(n)), Some(n));
+        }
+        assert_eq!(base36(71), "1z");
+        assert_eq!(from_base36(""), None);
+        assert_eq!(from_base36("-1"), None);
+    }
+}
@@ -0,0 +1,45 @@
+use js_sys::Math;
+use serde::{Deserialize, Serialize};
+
+/// Small seedable PRNG (mulberry32) so a game can be regenerated from its seed.
+///
+/// The state is a `u32` so it survives the round trip through JS numbers in
+/// saved state and URL parameters.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Self {
+        Self { state: seed }
+    }
+
+    /// Seed that regenerates everything this generator produces from here on.
+    pub fn state(&self) -> u32 {
+        self.state
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state = self.state.wrapping_add(0x6D2B_79F5);
+        let mut t = self.state;
+        t = (t ^ (t >> 15)).wrapping_mul(t | 1);
+        t ^= t.wrapping_add((t ^ (t >> 7)).wrapping_mul(t | 0x3D));
+        t ^ (t >> 14)
+    }
+
+    /// Uniform float in `[0, 1)`, a drop-in replacement for `Math.random()`.
+    pub fn random(&mut self) -> f64 {
+        f64::from(self.next_u32()) / 4_294_967_296.0
+    }
+
+    /// Uniform integer in `[0, max)`; returns 0 when `max` is 0.
+    pub fn below(&mut self, max: usize) -> usize {
+        (self.random() * max as f64) as usize
+    }
+}
+
+/// Fresh seed for a new session.
+pub fn random_seed() -> u32 {
+    (Math::random() * 4_294_967_296.0) as u32
+}
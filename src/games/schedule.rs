@@ -0,0 +1,19 @@
+//! Level schedules: a game's per-level settings as a JSON table, so its
+//! difficulty curve can be tuned without code changes. Row `n` describes
+//! level `n + 1`, and levels past the end of the table repeat its last row.
+
+use serde::de::DeserializeOwned;
+
+/// The rows of the schedule in `json`. Schedules are built into the game,
+/// so a malformed or empty one is a bug rather than bad input.
+pub fn schedule<T: DeserializeOwned>(json: &str) -> Vec<T> {
+    let rows: Vec<T> =
+        serde_json::from_str(json).expect("a level schedule should be a JSON array of rows");
+    assert!(!rows.is_empty(), "a level schedule needs at least one row");
+    rows
+}
+
+/// The row of `schedule` for `level`, counted from 1.
+pub fn stage<T>(schedule: &[T], level: usize) -> &T {
+    &schedule[level.saturating_sub(1).min(schedule.len() - 1)]
+}
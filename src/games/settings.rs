@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use std::{cell::Cell, collections::HashMap};
+use wasm_bindgen::prelude::*;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+
+/// Every game's timer settings are saved together under this key, by the
+/// game's code letter (as in replay and puzzle codes).
+const STORAGE_KEY: &str = "timer_settings";
+/// Extra time per level above the first when time grows with the level, as
+/// a share of the base limit.
+const GROWTH_PER_LEVEL: f64 = 0.1;
+/// Shortest and longest base limit the settings accept, in seconds.
+const MIN_SECONDS: u32 = 5;
+const MAX_SECONDS: u32 = 3600;
+
+thread_local! {
+    static INSTALLED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// How a game's time limit is set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Timing {
+    /// The same limit at every level.
+    #[default]
+    Fixed,
+    /// The limit grows with the level.
+    Scaled,
+    /// Relaxed play with no time limit.
+    Untimed,
+}
+
+impl Timing {
+    /// Value of the matching `#timing` option.
+    fn name(self) -> &'static str {
+        match self {
+            Timing::Fixed => "fixed",
+            Timing::Scaled => "scaled",
+            Timing::Untimed => "untimed",
+        }
+    }
+
+    fn from_name(name: &str) -> Self {
+        match name {
+            "scaled" => Timing::Scaled,
+            "untimed" => Timing::Untimed,
+            _ => Timing::Fixed,
+        }
+    }
+}
+
+/// One game's timer settings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimerSettings {
+    #[serde(default)]
+    pub timing: Timing,
+    /// Base limit in seconds, or `None` for the game's own.
+    #[serde(default)]
+    pub seconds: Option<u32>,
+}
+
+impl TimerSettings {
+    /// Seconds allowed at `level`, given the game's own limit for it, or
+    /// `None` when untimed.
+    pub fn limit(&self, default: u32, level: usize) -> Option<u32> {
+        let base = self.seconds.unwrap_or(default);
+        match self.timing {
+            Timing::Fixed => Some(base),
+            Timing::Scaled => {
+                let growth = 1.0 + GROWTH_PER_LEVEL * level.saturating_sub(1) as f64;
+                Some((f64::from(base) * growth).round() as u32)
+            }
+            Timing::Untimed => None,
+        }
+    }
+}
+
+fn storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+fn load_all() -> HashMap<String, TimerSettings> {
+    storage()
+        .and_then(|storage| storage.get_item(STORAGE_KEY).ok().flatten())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// The saved settings for `game`, or the defaults.
+pub fn load(game: char) -> TimerSettings {
+    load_all()
+        .get(&game.to_string())
+        .copied()
+        .unwrap_or_default()
+}
+
+pub fn save(game: char, settings: TimerSettings) {
+    let mut all = load_all();
+    all.insert(game.to_string(), settings);
+    if let (Some(storage), Ok(json)) = (storage(), serde_json::to_string(&all)) {
+        let _ = storage.set_item(STORAGE_KEY, &json);
+    }
+}
+
+/// Seconds `game` allows at `level`, given its own limit, or `None` when the
+/// player has chosen untimed play.
+pub fn time_limit(game: char, default: u32, level: usize) -> Option<u32> {
+    load(game).limit(default, level)
+}
+
+/// Shows `game`'s settings in the page's `#timing` select and `#time-limit`
+/// input and saves any change to them. Changes apply from the next round or
+/// level.
+pub fn install(game: char) -> Result<(), JsValue> {
+    // Games restart themselves on every level; the listeners only go on once
+    if INSTALLED.with(|installed| installed.replace(true)) {
+        return Ok(());
+    }
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .expect("no document exists");
+    let (Some(select), Some(input)) = (
+        document.get_element_by_id("timing"),
+        document.get_element_by_id("time-limit"),
+    ) else {
+        return Ok(());
+    };
+    let select: HtmlSelectElement = select.dyn_into()?;
+    let input: HtmlInputElement = input.dyn_into()?;
+
+    let current = load(game);
+    select.set_value(current.timing.name());
+    input.set_value(&current.seconds.map_or_else(String::new, |s| s.to_string()));
+
+    let handler = {
+        let (select, input) = (select.clone(), input.clone());
+        Closure::wrap(Box::new(move |_event: web_sys::Event| {
+            save(
+                game,
+                TimerSettings {
+                    timing: Timing::from_name(&select.value()),
+                    seconds: input
+                        .value()
+                        .parse()
+                        .ok()
+                        .map(|s: u32| s.clamp(MIN_SECONDS, MAX_SECONDS)),
+                },
+            );
+        }) as Box<dyn FnMut(_)>)
+    };
+    select.add_event_listener_with_callback("change", handler.as_ref().unchecked_ref())?;
+    input.add_event_listener_with_callback("change", handler.as_ref().unchecked_ref())?;
+    handler.forget();
+    Ok(())
+}
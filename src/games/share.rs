@@ -0,0 +1,174 @@
+use super::recording::{base36, from_base36};
+use std::convert::TryFrom;
+use web_sys::Document;
+
+/// How the sender did on a shared puzzle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub moves: usize,
+    pub seconds: u32,
+}
+
+/// One puzzle as a `?puzzle=` code: the game and the seed and level that
+/// regenerate it, plus the sender's result if they finished it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Puzzle {
+    pub game: char,
+    pub seed: u32,
+    pub level: usize,
+    /// Game-specific layout option, such as Perception's floor count; 1 for
+    /// the standard layout.
+    pub variant: usize,
+    pub outcome: Option<Outcome>,
+}
+
+impl Puzzle {
+    pub fn new(game: char, seed: u32, level: usize) -> Self {
+        Self {
+            game,
+            seed,
+            level,
+            variant: 1,
+            outcome: None,
+        }
+    }
+
+    #[must_use]
+    pub fn variant(mut self, variant: usize) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    #[must_use]
+    pub fn solved(mut self, moves: usize, seconds: u32) -> Self {
+        self.outcome = Some(Outcome { moves, seconds });
+        self
+    }
+
+    /// `<game><seed>.<level>`, with `-<variant>` for anything but the
+    /// standard layout and followed by `.<moves>.<seconds>` once solved,
+    /// numbers in base 36 as in replay codes.
+    pub fn encode(&self) -> String {
+        let mut out = format!(
+            "{}{}.{}",
+            self.game,
+            base36(self.seed as usize),
+            base36(self.level)
+        );
+        if self.variant != 1 {
+            out.push('-');
+            out.push_str(&base36(self.variant));
+        }
+        if let Some(outcome) = self.outcome {
+            out.push('.');
+            out.push_str(&base36(outcome.moves));
+            out.push('.');
+            out.push_str(&base36(outcome.seconds as usize));
+        }
+        out
+    }
+
+    pub fn decode(code: &str) -> Option<Self> {
+        let mut sections = code.split('.');
+        let header = sections.next()?;
+        let game = header.chars().next()?;
+        let seed = u32::try_from(from_base36(&header[game.len_utf8()..])?).ok()?;
+        let level_section = sections.next()?;
+        let (level, variant) = match level_section.split_once('-') {
+            Some((level, variant)) => (level, from_base36(variant)?),
+            None => (level_section, 1),
+        };
+        let level = from_base36(level)?.max(1);
+        let outcome = match (sections.next(), sections.next()) {
+            (Some(moves), Some(seconds)) => Some(Outcome {
+                moves: from_base36(moves)?,
+                seconds: u32::try_from(from_base36(seconds)?).ok()?,
+            }),
+            _ => None,
+        };
+        Some(Self {
+            game,
+            seed,
+            level,
+            variant,
+            outcome,
+        })
+    }
+
+    /// Points the page's `#share-link` at this puzzle and shows it.
+    pub fn publish(&self, document: &Document) {
+        if let Some(link) = document.get_element_by_id("share-link") {
+            let _ = link.set_attribute("href", &format!("?puzzle={}", self.encode()));
+            let _ = link.remove_attribute("hidden");
+        }
+    }
+
+    /// Tells the receiving player what they are playing and the score to beat.
+    pub fn announce(&self, document: &Document) {
+        if let Some(banner) = document.get_element_by_id("share-banner") {
+            let text = match self.outcome {
+                Some(Outcome { moves, seconds }) => format!(
+                    "Shared puzzle: your friend solved it in {} moves and {}:{:02}",
+                    moves,
+                    seconds / 60,
+                    seconds % 60
+                ),
+                None => "Shared puzzle".to_string(),
+            };
+            banner.set_text_content(Some(&text));
+            let _ = banner.remove_attribute("hidden");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn puzzles_round_trip() {
+        for puzzle in [
+            Puzzle::new('p', 0, 1),
+            Puzzle::new('m', u32::MAX, 40),
+            Puzzle::new('p', 98_765, 12).variant(3),
+            Puzzle::new('p', 98_765, 12).solved(57, 125),
+            Puzzle::new('p', 98_765, 12).variant(2).solved(0, 0),
+        ] {
+            assert_eq!(Puzzle::decode(&puzzle.encode()), Some(puzzle));
+        }
+    }
+
+    #[test]
+    fn codes_are_compact() {
+        assert_eq!(Puzzle::new('p', 71, 3).encode(), "p1z.3");
+        assert_eq!(Puzzle::new('p', 71, 3).variant(2).encode(), "p1z.3-2");
+        assert_eq!(
+            Puzzle::new('p', 71, 3).solved(40, 90).encode(),
+            "p1z.3.14.2i"
+        );
+    }
+
+    #[test]
+    fn level_zero_is_read_as_level_one() {
+        assert_eq!(Puzzle::decode("p1z.0").map(|p| p.level), Some(1));
+    }
+
+    #[test]
+    fn malformed_codes_are_rejected() {
+        for code in [
+            "",
+            "p",
+            "p1z",
+            "p1z.",
+            "p.3",
+            "pzzzzzzzzz.3",
+            "p1z.!",
+            "p1z.3-",
+            "p1z.3-x!",
+            "p1z.3.14.",
+            "p1z.3.14.!",
+        ] {
+            assert_eq!(Puzzle::decode(code), None, "{code:?} decoded");
+        }
+    }
+}
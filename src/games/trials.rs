@@ -0,0 +1,469 @@
+//! The same-or-mirror judgement tasks' shared session: pairs shown one after
+//! another, answers timed and scored, the level moved by a staircase, and
+//! reaction time and accuracy logged trial by trial and summarised by the
+//! angle between the two figures. Each task only draws its own pairs.
+
+use super::{
+    clock,
+    rng::{self, Rng},
+};
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::BTreeMap, fmt::Write, rc::Rc};
+use wasm_bindgen::prelude::*;
+use web_sys::{Document, Event, KeyboardEvent};
+
+/// Trials kept per task; the oldest are dropped first.
+const MAX_TRIALS: usize = 1000;
+/// Trials per block, and how many of them must be right to go up a level;
+/// `DEMOTE_AT` or fewer sends the player down one.
+const BLOCK_SIZE: usize = 6;
+const PROMOTE_AT: usize = 5;
+const DEMOTE_AT: usize = 3;
+/// How long the verdict on an answer shows before the next pair.
+const FEEDBACK_MS: i32 = 800;
+
+/// One judgement task: where it keeps its progress and how it draws a pair.
+pub struct Task {
+    /// Storage keys for the paused flag, the level reached and the trial log.
+    pub pause_key: &'static str,
+    pub level_key: &'static str,
+    pub trials_key: &'static str,
+    pub max_level: usize,
+    /// Draws a fresh pair for a level into `#figure-left` and
+    /// `#figure-right`, and returns the angle between the figures and
+    /// whether the second is the mirror image.
+    pub draw: fn(&Document, &mut Rng, usize) -> (u32, bool),
+}
+
+/// One judgement: a pair of figures shown and the player's answer.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Trial {
+    /// Degrees the second figure was turned through.
+    pub angle: u32,
+    /// The second figure was a mirror image rather than the same object.
+    pub mirrored: bool,
+    pub correct: bool,
+    /// Milliseconds of game time from showing the pair to the answer.
+    pub reaction_ms: u32,
+    /// Difficulty level the trial was played at.
+    pub level: usize,
+}
+
+/// Trials at one angle.
+struct AngleSummary {
+    angle: u32,
+    trials: usize,
+    correct: usize,
+    /// Mean reaction time over the correct answers only, as is usual for
+    /// the task, or `None` before the first.
+    mean_reaction_ms: Option<f64>,
+}
+
+/// The level a task is played at, moved after every block of trials by how
+/// many were right, and saved so the next visit starts where this one left.
+struct Staircase {
+    key: &'static str,
+    max_level: usize,
+    level: usize,
+    /// Whether each answer so far in this block was right.
+    block: Vec<bool>,
+}
+
+fn storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+impl Staircase {
+    /// The level saved under `key`, or the first.
+    fn load(key: &'static str, max_level: usize) -> Self {
+        let level = storage()
+            .and_then(|storage| storage.get_item(key).ok().flatten())
+            .and_then(|level| level.parse().ok())
+            .map_or(1, |level: usize| level.clamp(1, max_level));
+        Self {
+            key,
+            max_level,
+            level,
+            block: Vec::new(),
+        }
+    }
+
+    fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Counts an answer, and at the end of a block moves and saves the
+    /// level.
+    fn record(&mut self, correct: bool) {
+        if !self.count(correct) {
+            return;
+        }
+        if let Some(storage) = storage() {
+            let _ = storage.set_item(self.key, &self.level.to_string());
+        }
+    }
+
+    /// Counts an answer, moving the level if it ends a block. Returns
+    /// whether it did.
+    fn count(&mut self, correct: bool) -> bool {
+        self.block.push(correct);
+        if self.block.len() < BLOCK_SIZE {
+            return false;
+        }
+        let right = self.block.iter().filter(|&&correct| correct).count();
+        if right >= PROMOTE_AT {
+            self.level = (self.level + 1).min(self.max_level);
+        } else if right <= DEMOTE_AT {
+            self.level = self.level.saturating_sub(1).max(1);
+        }
+        self.block.clear();
+        true
+    }
+}
+
+/// The pair on screen, awaiting an answer.
+struct Pair {
+    angle: u32,
+    mirrored: bool,
+    /// Game time the pair appeared.
+    shown_at: f64,
+}
+
+struct State {
+    task: &'static Task,
+    rng: Rng,
+    staircase: Staircase,
+    /// `None` between an answer and the next pair.
+    pair: Option<Pair>,
+}
+
+/// A task being played on the page.
+#[derive(Clone)]
+pub struct Session {
+    state: Rc<RefCell<State>>,
+    document: Document,
+}
+
+impl Session {
+    #[must_use]
+    pub fn new(task: &'static Task) -> Self {
+        let document = web_sys::window()
+            .and_then(|w| w.document())
+            .expect("no document exists");
+        Self {
+            state: Rc::new(RefCell::new(State {
+                task,
+                rng: Rng::new(rng::random_seed()),
+                staircase: Staircase::load(task.level_key, task.max_level),
+                pair: None,
+            })),
+            document,
+        }
+    }
+
+    /// Wires up the answer controls and the pause key, shows the log so far
+    /// and the first pair.
+    pub fn start(&self) -> Result<(), JsValue> {
+        let task = self.state.borrow().task;
+        clock::install(task.pause_key)?;
+        let session = self.clone();
+        bind_answers(&self.document, move |mirrored| session.answer(mirrored))?;
+        render(&self.document, &load(task.trials_key));
+        self.show_pair();
+        Ok(())
+    }
+
+    /// Draws a fresh pair for the current level and starts timing the
+    /// answer.
+    fn show_pair(&self) {
+        let mut state = self.state.borrow_mut();
+        let state = &mut *state;
+        let level = state.staircase.level();
+        let (angle, mirrored) = (state.task.draw)(&self.document, &mut state.rng, level);
+        if let Some(feedback) = self.document.get_element_by_id("feedback") {
+            feedback.set_text_content(None);
+        }
+        if let Some(element) = self.document.get_element_by_id("level") {
+            element.set_text_content(Some(&level.to_string()));
+        }
+        state.pair = Some(Pair {
+            angle,
+            mirrored,
+            shown_at: clock::now(),
+        });
+    }
+
+    /// Scores the player's verdict on the pair on screen, logs the trial,
+    /// moves the level at the end of a block and shows the next pair after
+    /// a moment. Ignored while paused or between pairs.
+    fn answer(&self, mirrored: bool) {
+        if !clock::is_running() {
+            return;
+        }
+        let mut state = self.state.borrow_mut();
+        let Some(pair) = state.pair.take() else {
+            return;
+        };
+        let correct = mirrored == pair.mirrored;
+        let trial = Trial {
+            angle: pair.angle,
+            mirrored: pair.mirrored,
+            correct,
+            reaction_ms: (clock::now() - pair.shown_at).max(0.0).round() as u32,
+            level: state.staircase.level(),
+        };
+        render(&self.document, &record(state.task.trials_key, trial));
+        state.staircase.record(correct);
+        drop(state);
+        show_verdict(&self.document, &trial);
+
+        let session = self.clone();
+        let callback = Closure::once(move || session.show_pair());
+        if let Some(window) = web_sys::window() {
+            let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                callback.as_ref().unchecked_ref(),
+                FEEDBACK_MS,
+            );
+        }
+        callback.forget();
+    }
+}
+
+/// Calls `answer` with whether the player judged the pair a mirror image,
+/// from the page's `#same` and `#mirror` buttons or the S and M keys.
+fn bind_answers(
+    document: &Document,
+    answer: impl Fn(bool) + Clone + 'static,
+) -> Result<(), JsValue> {
+    for (id, mirrored) in [("same", false), ("mirror", true)].iter().copied() {
+        if let Some(button) = document.get_element_by_id(id) {
+            let answer = answer.clone();
+            let callback = Closure::wrap(Box::new(move |_: Event| {
+                answer(mirrored);
+            }) as Box<dyn FnMut(Event)>);
+            button.add_event_listener_with_callback("click", callback.as_ref().unchecked_ref())?;
+            callback.forget();
+        }
+    }
+
+    let key_callback = Closure::wrap(Box::new(move |event: KeyboardEvent| {
+        if event.ctrl_key() || event.meta_key() || event.alt_key() {
+            return;
+        }
+        match event.key().to_lowercase().as_str() {
+            "s" => answer(false),
+            "m" => answer(true),
+            _ => {}
+        }
+    }) as Box<dyn FnMut(KeyboardEvent)>);
+    document.add_event_listener_with_callback("keydown", key_callback.as_ref().unchecked_ref())?;
+    key_callback.forget();
+    Ok(())
+}
+
+/// Every trial logged under `key`, oldest first.
+fn load(key: &str) -> Vec<Trial> {
+    storage()
+        .and_then(|storage| storage.get_item(key).ok().flatten())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Adds `trial` to the log under `key` and returns the whole log.
+fn record(key: &str, trial: Trial) -> Vec<Trial> {
+    let mut trials = load(key);
+    trials.push(trial);
+    let excess = trials.len().saturating_sub(MAX_TRIALS);
+    trials.drain(..excess);
+    if let (Some(storage), Ok(json)) = (storage(), serde_json::to_string(&trials)) {
+        let _ = storage.set_item(key, &json);
+    }
+    trials
+}
+
+fn by_angle(trials: &[Trial]) -> Vec<AngleSummary> {
+    let mut angles: BTreeMap<u32, Vec<&Trial>> = BTreeMap::new();
+    for trial in trials {
+        angles.entry(trial.angle).or_default().push(trial);
+    }
+    angles
+        .into_iter()
+        .map(|(angle, trials)| {
+            let times: Vec<f64> = trials
+                .iter()
+                .filter(|trial| trial.correct)
+                .map(|trial| f64::from(trial.reaction_ms))
+                .collect();
+            AngleSummary {
+                angle,
+                trials: trials.len(),
+                correct: times.len(),
+                mean_reaction_ms: (!times.is_empty())
+                    .then(|| times.iter().sum::<f64>() / times.len() as f64),
+            }
+        })
+        .collect()
+}
+
+/// Extra milliseconds each degree of rotation adds to a correct answer, by
+/// least squares over the correct trials; `None` until they cover two angles.
+fn slope(trials: &[Trial]) -> Option<f64> {
+    let points: Vec<(f64, f64)> = trials
+        .iter()
+        .filter(|trial| trial.correct)
+        .map(|trial| (f64::from(trial.angle), f64::from(trial.reaction_ms)))
+        .collect();
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|&(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / n;
+    let spread: f64 = points.iter().map(|&(x, _)| (x - mean_x).powi(2)).sum();
+    let covariance: f64 = points
+        .iter()
+        .map(|&(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    (spread > 0.0).then(|| covariance / spread)
+}
+
+/// Tells the player in `#feedback` whether they were right and how long
+/// they took.
+fn show_verdict(document: &Document, trial: &Trial) {
+    if let Some(feedback) = document.get_element_by_id("feedback") {
+        let verdict = if trial.correct { "Correct" } else { "Wrong" };
+        feedback.set_text_content(Some(&format!("{verdict} – {} ms", trial.reaction_ms)));
+    }
+}
+
+/// Fills the page's `#trial-stats` table with a row per angle, and
+/// `#rt-slope` with how much each degree slows the player down.
+fn render(document: &Document, trials: &[Trial]) {
+    if let Some(table) = document.get_element_by_id("trial-stats") {
+        let mut rows = String::new();
+        for summary in by_angle(trials) {
+            let mean = summary
+                .mean_reaction_ms
+                .map_or_else(|| "–".to_string(), |ms| format!("{ms:.0} ms"));
+            let _ = write!(
+                rows,
+                "<tr><td>{}°</td><td>{}</td><td>{}%</td><td>{}</td></tr>",
+                summary.angle,
+                summary.trials,
+                summary.correct * 100 / summary.trials,
+                mean
+            );
+        }
+        table.set_inner_html(&rows);
+    }
+    if let Some(line) = document.get_element_by_id("rt-slope") {
+        let text = slope(trials).map_or_else(
+            || {
+                "Answer pairs at a few different angles to see how rotation slows you down."
+                    .to_string()
+            },
+            |ms| format!("Each degree of rotation adds about {ms:.1} ms to a correct answer."),
+        );
+        line.set_text_content(Some(&text));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn staircase(level: usize) -> Staircase {
+        Staircase {
+            key: "test_level",
+            max_level: 3,
+            level,
+            block: Vec::new(),
+        }
+    }
+
+    /// Plays a block with `right` correct answers, and returns the level
+    /// after it.
+    fn block(staircase: &mut Staircase, right: usize) -> usize {
+        for answer in 0..BLOCK_SIZE {
+            staircase.count(answer < right);
+        }
+        staircase.level()
+    }
+
+    fn trial(angle: u32, correct: bool, reaction_ms: u32) -> Trial {
+        Trial {
+            angle,
+            mirrored: false,
+            correct,
+            reaction_ms,
+            level: 1,
+        }
+    }
+
+    #[test]
+    fn the_level_only_moves_at_the_end_of_a_block() {
+        let mut staircase = staircase(2);
+        for _ in 1..BLOCK_SIZE {
+            assert!(!staircase.count(true));
+            assert_eq!(staircase.level(), 2);
+        }
+        assert!(staircase.count(true));
+        assert_eq!(staircase.level(), 3);
+    }
+
+    #[test]
+    fn good_blocks_promote_and_poor_ones_demote() {
+        let mut staircase = staircase(2);
+        assert_eq!(block(&mut staircase, PROMOTE_AT), 3);
+        assert_eq!(block(&mut staircase, DEMOTE_AT + 1), 3);
+        assert_eq!(block(&mut staircase, DEMOTE_AT), 2);
+    }
+
+    #[test]
+    fn the_level_stays_in_range() {
+        let mut top = staircase(3);
+        assert_eq!(block(&mut top, BLOCK_SIZE), 3);
+        let mut bottom = staircase(1);
+        assert_eq!(block(&mut bottom, 0), 1);
+    }
+
+    #[test]
+    fn summaries_time_correct_answers_only() {
+        let trials = [
+            trial(0, true, 600),
+            trial(0, true, 800),
+            trial(0, false, 5_000),
+            trial(60, false, 900),
+        ];
+        let summaries = by_angle(&trials);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(
+            (
+                summaries[0].angle,
+                summaries[0].trials,
+                summaries[0].correct
+            ),
+            (0, 3, 2)
+        );
+        assert_eq!(summaries[0].mean_reaction_ms, Some(700.0));
+        assert_eq!(
+            (
+                summaries[1].angle,
+                summaries[1].trials,
+                summaries[1].correct
+            ),
+            (60, 1, 0)
+        );
+        assert_eq!(summaries[1].mean_reaction_ms, None);
+    }
+
+    #[test]
+    fn slope_needs_two_angles() {
+        assert_eq!(slope(&[]), None);
+        assert_eq!(slope(&[trial(40, true, 900), trial(40, true, 1_100)]), None);
+        let trials = [
+            trial(0, true, 500),
+            trial(90, true, 1_400),
+            trial(180, true, 2_300),
+            trial(180, false, 100),
+        ];
+        assert!((slope(&trials).unwrap() - 10.0).abs() < 1e-9);
+    }
+}
@@ -2,7 +2,12 @@ mod games;
 
 pub use games::numeracy::Numeracy;
 pub use games::perception::Perception;
-pub use games::mental_rotation::MentalRotation;
+pub use games::mental_rotation::{MentalRotation, PolyominoRotation};
+pub use games::cube_rotation::CubeRotation;
+use games::{
+    recording::{self, Recording},
+    share::Puzzle,
+};
 use wasm_bindgen::{prelude::*, JsValue};
 
 #[cfg(feature = "wee_alloc")]
@@ -19,18 +24,53 @@ pub fn main_js() -> Result<(), JsValue> {
     let location = window.location();
     let path = location.pathname().expect("pathname should exist");
 
+    // A `?replay=` code re-drives the page's game from a recorded session
+    let replay = recording::query_param("replay").and_then(|code| Recording::decode(&code));
+    // and a `?puzzle=` code starts a fresh session on a shared puzzle
+    let puzzle = recording::query_param("puzzle").and_then(|code| Puzzle::decode(&code));
+
     // Return the created instance rather than discarding it
-    match path.as_str() {
-        "/numeracy" => {
+    match (path.as_str(), replay, puzzle) {
+        ("/numeracy", Some(recording), _) if recording.game == 'n' => {
+            Numeracy::replay(&recording)?;
+        }
+        ("/numeracy", _, Some(puzzle)) if puzzle.game == 'n' => {
+            Numeracy::play_puzzle(&puzzle)?;
+        }
+        ("/numeracy", _, _) => {
             let game = Numeracy::new()?;
             game.start()?;
         }
-        "/mental-rotation" => {
+        ("/mental-rotation", Some(recording), _) if recording.game == 'm' => {
+            MentalRotation::replay(&recording)?;
+        }
+        ("/mental-rotation", _, Some(puzzle)) if puzzle.game == 'm' => {
+            MentalRotation::play_puzzle(&puzzle)?;
+        }
+        ("/mental-rotation", _, _) => {
             let game = MentalRotation::new(1);
             game.start()?;
         }
+        ("/polyomino-rotation", _, _) => {
+            PolyominoRotation::new().start()?;
+        }
+        ("/cube-rotation", _, _) => {
+            CubeRotation::new().start()?;
+        }
+        (_, Some(recording), _) if recording.game == 'p' => {
+            Perception::replay(&recording)?;
+        }
+        (_, _, Some(puzzle)) if puzzle.game == 'p' => {
+            Perception::play_puzzle(&puzzle)?;
+        }
         _ => {
-            Perception::new()?;
+            // A `?maze=` drawing starts a session on that hand-authored maze
+            match recording::query_param("maze") {
+                Some(text) => Perception::play_ascii(&text)?,
+                None => {
+                    Perception::new()?;
+                }
+            }
         }
     }
     Ok(())